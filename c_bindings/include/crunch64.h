@@ -3,9 +3,17 @@
 #pragma once
 
 #include "crunch64/error.h"
+#include "crunch64/format.h"
 #include "crunch64/mio0.h"
 #include "crunch64/yay0.h"
 #include "crunch64/yaz0.h"
 #include "crunch64/gzip.h"
+#include "crunch64/zlib.h"
+#include "crunch64/rare.h"
+#include "crunch64/vpk0.h"
+#include "crunch64/lzkn64.h"
+#include "crunch64/lz77.h"
+#include "crunch64/rle.h"
+#include "crunch64/huffman.h"
 
 #endif
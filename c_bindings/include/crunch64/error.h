@@ -19,6 +19,21 @@ typedef enum Crunch64Error {
     Crunch64Error_OutOfBounds,
     Crunch64Error_NullPointer,
     Crunch64Error_InvalidCompressionLevel,
+    Crunch64Error_InvalidGzipHeader,
+    Crunch64Error_InvalidDeflateStream,
+    Crunch64Error_ChecksumMismatch,
+    Crunch64Error_InvalidZlibHeader,
+    Crunch64Error_InvalidRareHeader,
+    Crunch64Error_TruncatedInput,
+    Crunch64Error_InvalidBackReference,
+    Crunch64Error_OutputOverrun,
+    Crunch64Error_InvalidVpk0Header,
+    Crunch64Error_InvalidLzkn64Header,
+    Crunch64Error_InvalidCommand,
+    Crunch64Error_InvalidLz77Header,
+    Crunch64Error_InvalidRleHeader,
+    Crunch64Error_InvalidHuffmanHeader,
+    Crunch64Error_SizeMismatch,
 } Crunch64Error;
 
 #ifdef __cplusplus
@@ -0,0 +1,89 @@
+#ifndef CRUNCH64_FORMAT_H
+#define CRUNCH64_FORMAT_H
+#pragma once
+
+#include <stddef.h>
+#include <stdint.h>
+
+#include "error.h"
+
+#ifdef __cplusplus
+extern "C"
+{
+#endif
+
+/* This needs to be synced with the Rust equivalent in `src/lib.rs` */
+typedef enum Crunch64Format {
+    Crunch64Format_Yay0,
+    Crunch64Format_Yaz0,
+    Crunch64Format_Mio0,
+    Crunch64Format_Gzip,
+    Crunch64Format_Zlib,
+    Crunch64Format_Deflate,
+    Crunch64Format_Rare,
+    Crunch64Format_Vpk0,
+    Crunch64Format_Lzkn64,
+    Crunch64Format_Lz77,
+    Crunch64Format_Rle,
+    Crunch64Format_Huffman,
+} Crunch64Format;
+
+/**
+ * @brief Get a size big enough to allocate a buffer that can fit the uncompressed data produced by uncompressing `src`
+ * with the given `format`.
+ *
+ * Formats that don't store the uncompressed size (zlib, raw DEFLATE and LZKN64) have to decompress the whole data to find it.
+ *
+ * Returns `Crunch64Error_UnsupportedCompressionType` if `format` is not a valid `Crunch64Format`.
+ *
+ * @param format Compression format of `src`.
+ * @param dst_size[out] Will be set to the requested size.
+ * @param src_len Size of `src`
+ * @param src[in] Compressed data
+ */
+Crunch64Error crunch64_decompress_bound(Crunch64Format format, size_t *dst_size, size_t src_len, const uint8_t *const src);
+
+/**
+ * @brief Decompresses the data pointed by `src` with the given `format` and puts that data into `dst`.
+ *
+ * Works like the format-specific decompression functions (for example `crunch64_yaz0_decompress`). The size of `dst`
+ * can be obtained with `crunch64_decompress_bound`.
+ *
+ * @param format Compression format of `src`.
+ * @param dst_len[in,out] Will be set to the decompressed size. It should point to the size of the `dst` buffer when the function is called.
+ * @param dst[out] Pointer to buffer big enough to hold the decompressed data.
+ * @param src_len The length of the data pointed by `src`.
+ * @param src[in] Pointer to compressed data.
+ */
+Crunch64Error crunch64_decompress(Crunch64Format format, size_t *dst_len, uint8_t *dst, size_t src_len, const uint8_t *const src);
+
+/**
+ * @brief Get a size big enough to allocate a buffer that can fit the compressed data produced by compressing `src`
+ * with the given `format`.
+ *
+ * @param format Compression format to use.
+ * @param dst_size[out] Will be set to the requested size.
+ * @param src_len Size of `src`
+ * @param src[in] Data that would be compressed
+ */
+Crunch64Error crunch64_compress_bound(Crunch64Format format, size_t *dst_size, size_t src_len, const uint8_t *const src);
+
+/**
+ * @brief Compresses the data pointed by `src` with the given `format` and puts that data into `dst`.
+ *
+ * Works like the format-specific compression functions, using the default options for each format (level 9 for the
+ * DEFLATE-based formats). The size of `dst` can be obtained with `crunch64_compress_bound`.
+ *
+ * @param format Compression format to use.
+ * @param dst_len[in,out] Will be set to the compressed size. It should point to the size of the `dst` buffer when the function is called.
+ * @param dst[out] Pointer to buffer big enough to hold the compressed data.
+ * @param src_len The length of the data pointed by `src`.
+ * @param src[in] Pointer to the decompressed data.
+ */
+Crunch64Error crunch64_compress(Crunch64Format format, size_t *dst_len, uint8_t *dst, size_t src_len, const uint8_t *const src);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -13,6 +13,44 @@ extern "C"
 {
 #endif
 
+/**
+ * @brief Get a size big enough to allocate a buffer that can fit the uncompressed data produced by uncompressing `src`.
+ *
+ * The compressed data must end with the gzip footer, since the uncompressed size is read from it.
+ *
+ * Returning `true` means the function succeeded and the requested size was put in `dst_size`.
+ *
+ * If this function fails to calculate said size then it will return `false` and `dst_size` may have garbage data.
+ *
+ * @param dst_size[out] Will be set to the requested size.
+ * @param src_len Size of `src`
+ * @param src[in] Compressed gzip data
+ */
+Crunch64Error crunch64_gzip_decompress_bound(size_t *dst_size, size_t src_len, const uint8_t *const src);
+
+/**
+ * @brief Decompresses the data pointed by `src` and puts that data into `dst`.
+ *
+ * The `dst` should point to a buffer big enough to hold the decompressed data. To know how big said buffer must be
+ * refer to `crunch64_gzip_decompress_bound`.
+ *
+ * When this function is called, `dst_len` must point to the size of the `dst` pointer, allowing for range checking
+ * and avoiding to write out of bounds.
+ *
+ * If the function succeedes it returns `true` and it puts the decompressed data on `dst` and the actual decompressed
+ * size is put on `dst_len`.
+ *
+ * If this function fails it will return `false`. `dst_size` and `dst` may have garbage data.
+ *
+ * The gzip header is optional, but the gzip footer is required and its checksum is verified.
+ *
+ * @param dst_len[in,out] Will be set to the decompressed size. It should point to the size of the `dst` buffer when the function is called.
+ * @param dst[out] Pointer to buffer big enough to hold the decompressed data.
+ * @param src_len The length of the data pointed by `src`.
+ * @param src[in] Pointer to compressed data.
+ */
+Crunch64Error crunch64_gzip_decompress(size_t *dst_len, uint8_t *dst, size_t src_len, const uint8_t *const src);
+
 /**
  * @brief Get a size big enough to allocate a buffer that can fit the compressed data produced by compressing `src`.
  *
@@ -46,11 +84,51 @@ Crunch64Error crunch64_gzip_compress_bound(size_t *dst_size, size_t src_len, con
  * @param dst[out] Pointer to buffer big enough to hold the compressed data.
  * @param src_len The length of the data pointed by `src`.
  * @param src[in] Pointer to the decompressed data.
- * @param level Compression level (4-9).
+ * @param level Compression level (1-9).
  * @param small_mem If `true` then the function will output compressed blocks more often.
  */
 Crunch64Error crunch64_gzip_compress(size_t *dst_len, uint8_t *dst, size_t src_len, const uint8_t *const src, int level, bool small_mem);
 
+/**
+ * @brief Get a size big enough to allocate a buffer that can fit the uncompressed data produced by uncompressing the
+ * raw DEFLATE stream in `src`.
+ *
+ * Raw DEFLATE streams don't store the uncompressed size, so this function has to decompress the whole data to find it.
+ *
+ * @param dst_size[out] Will be set to the requested size.
+ * @param src_len Size of `src`
+ * @param src[in] Raw DEFLATE stream
+ */
+Crunch64Error crunch64_gzip_decompress_raw_bound(size_t *dst_size, size_t src_len, const uint8_t *const src);
+
+/**
+ * @brief Decompresses the raw DEFLATE stream pointed by `src` and puts that data into `dst`.
+ *
+ * Works like `crunch64_gzip_decompress`, but `src` must not have a gzip header or footer. Any data after the end of
+ * the stream is ignored.
+ *
+ * @param dst_len[in,out] Will be set to the decompressed size. It should point to the size of the `dst` buffer when the function is called.
+ * @param dst[out] Pointer to buffer big enough to hold the decompressed data.
+ * @param src_len The length of the data pointed by `src`.
+ * @param src[in] Pointer to compressed data.
+ */
+Crunch64Error crunch64_gzip_decompress_raw(size_t *dst_len, uint8_t *dst, size_t src_len, const uint8_t *const src);
+
+/**
+ * @brief Compresses the data pointed by `src` into a raw DEFLATE stream and puts that data into `dst`.
+ *
+ * Works like `crunch64_gzip_compress`, but `dst` will not include the gzip footer. `crunch64_gzip_compress_bound` can
+ * be used to know how big `dst` must be.
+ *
+ * @param dst_len[in,out] Will be set to the compressed size. It should point to the size of the `dst` buffer when the function is called.
+ * @param dst[out] Pointer to buffer big enough to hold the compressed data.
+ * @param src_len The length of the data pointed by `src`.
+ * @param src[in] Pointer to the decompressed data.
+ * @param level Compression level (1-9).
+ * @param small_mem If `true` then the function will output compressed blocks more often.
+ */
+Crunch64Error crunch64_gzip_compress_raw(size_t *dst_len, uint8_t *dst, size_t src_len, const uint8_t *const src, int level, bool small_mem);
+
 #ifdef __cplusplus
 }
 #endif
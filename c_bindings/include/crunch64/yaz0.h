@@ -87,6 +87,33 @@ Crunch64Error crunch64_yaz0_compress_bound(size_t *dst_size, size_t src_len, con
  */
 Crunch64Error crunch64_yaz0_compress(size_t *dst_len, uint8_t *dst, size_t src_len, const uint8_t *const src);
 
+/**
+ * @brief Same as `crunch64_yaz0_compress`, but finds the smallest possible compressed data instead of matching the
+ * original encoder.
+ *
+ * The result can be decompressed by the original game code too. This is much slower than `crunch64_yaz0_compress`.
+ *
+ * @param dst_len[in,out] Will be set to the compressed size. It should point to the size of the `dst` buffer when the function is called.
+ * @param dst[out] Pointer to buffer big enough to hold the compressed data.
+ * @param src_len The length of the data pointed by `src`.
+ * @param src[in] Pointer to the decompressed data.
+ */
+Crunch64Error crunch64_yaz0_compress_optimal(size_t *dst_len, uint8_t *dst, size_t src_len, const uint8_t *const src);
+
+/**
+ * @brief Same as `crunch64_yaz0_compress`, but stores `alignment` in the Yaz0 header.
+ *
+ * Later Nintendo tools store the alignment of the data at offset 8 of the header. It doesn't change the compressed data.
+ *
+ * @param dst_len[in,out] Will be set to the compressed size. It should point to the size of the `dst` buffer when the function is called.
+ * @param dst[out] Pointer to buffer big enough to hold the compressed data.
+ * @param src_len The length of the data pointed by `src`.
+ * @param src[in] Pointer to the decompressed data.
+ * @param alignment Data alignment to store in the header.
+ */
+Crunch64Error crunch64_yaz0_compress_aligned(size_t *dst_len, uint8_t *dst, size_t src_len, const uint8_t *const src,
+                                             uint32_t alignment);
+
 #ifdef __cplusplus
 }
 #endif
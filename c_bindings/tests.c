@@ -21,6 +21,22 @@ const char *const crunch64_error_str[] = {
     [Crunch64Error_ByteConversion] = "Byte conversion",
     [Crunch64Error_OutOfBounds] = "Out of bounds",
     [Crunch64Error_NullPointer] = "Null pointer",
+    [Crunch64Error_InvalidCompressionLevel] = "Invalid compression level",
+    [Crunch64Error_InvalidGzipHeader] = "Invalid gzip header",
+    [Crunch64Error_InvalidDeflateStream] = "Invalid DEFLATE stream",
+    [Crunch64Error_ChecksumMismatch] = "Checksum mismatch",
+    [Crunch64Error_InvalidZlibHeader] = "Invalid zlib header",
+    [Crunch64Error_InvalidRareHeader] = "Invalid Rare header",
+    [Crunch64Error_TruncatedInput] = "Truncated input",
+    [Crunch64Error_InvalidBackReference] = "Invalid back-reference",
+    [Crunch64Error_OutputOverrun] = "Output overrun",
+    [Crunch64Error_InvalidVpk0Header] = "Invalid vpk0 header",
+    [Crunch64Error_InvalidLzkn64Header] = "Invalid LZKN64 header",
+    [Crunch64Error_InvalidCommand] = "Invalid command",
+    [Crunch64Error_InvalidLz77Header] = "Invalid LZ77 header",
+    [Crunch64Error_InvalidRleHeader] = "Invalid RLE header",
+    [Crunch64Error_InvalidHuffmanHeader] = "Invalid Huffman header",
+    [Crunch64Error_SizeMismatch] = "Size mismatch",
 };
 
 const char *get_crunch64_error_str(Crunch64Error error) {
@@ -247,22 +263,130 @@ Crunch64Error gzip_compress(size_t *dst_size, uint8_t *dst, size_t src_size, con
     return crunch64_gzip_compress(dst_size, dst, src_size, src, gzip_level, gzip_small_mem);
 }
 
+Crunch64Error rare_compress(size_t *dst_size, uint8_t *dst, size_t src_size, const uint8_t *src) {
+    return crunch64_rare_compress(dst_size, dst, src_size, src, 9, false);
+}
+
+bool vpk0_two_sample;
+
+Crunch64Error vpk0_compress(size_t *dst_size, uint8_t *dst, size_t src_size, const uint8_t *src) {
+    return crunch64_vpk0_compress(dst_size, dst, src_size, src, vpk0_two_sample);
+}
+
+bool lz77_lz11;
+bool lz77_vram_safe;
+
+Crunch64Error lz77_compress(size_t *dst_size, uint8_t *dst, size_t src_size, const uint8_t *src) {
+    return crunch64_lz77_compress(dst_size, dst, src_size, src, lz77_lz11, lz77_vram_safe);
+}
+
+bool huffman_four_bit;
+
+Crunch64Error huffman_compress(size_t *dst_size, uint8_t *dst, size_t src_size, const uint8_t *src) {
+    return crunch64_huffman_compress(dst_size, dst, src_size, src, huffman_four_bit);
+}
+
+Crunch64Error yaz0_compress_aligned(size_t *dst_size, uint8_t *dst, size_t src_size, const uint8_t *src) {
+    // The fixtures don't store an alignment, so this must match `crunch64_yaz0_compress`
+    return crunch64_yaz0_compress_aligned(dst_size, dst, src_size, src, 0);
+}
+
+Crunch64Error zlib_compress(size_t *dst_size, uint8_t *dst, size_t src_size, const uint8_t *src) {
+    return crunch64_zlib_compress(dst_size, dst, src_size, src, 9, false);
+}
+
+Crunch64Format generic_format;
+
+Crunch64Error generic_compress_bound(size_t *dst_size, size_t src_len, const uint8_t *const src) {
+    return crunch64_compress_bound(generic_format, dst_size, src_len, src);
+}
+
+Crunch64Error generic_compress(size_t *dst_size, uint8_t *dst, size_t src_size, const uint8_t *src) {
+    return crunch64_compress(generic_format, dst_size, dst, src_size, src);
+}
+
+Crunch64Error generic_decompress_bound(size_t *dst_size, size_t src_len, const uint8_t *const src) {
+    return crunch64_decompress_bound(generic_format, dst_size, src_len, src);
+}
+
+Crunch64Error generic_decompress(size_t *dst_size, uint8_t *dst, size_t src_size, const uint8_t *src) {
+    return crunch64_decompress(generic_format, dst_size, dst, src_size, src);
+}
+
 int main(void) {
     run_tests("yay0", ".Yay0", crunch64_yay0_compress_bound, crunch64_yay0_compress, crunch64_yay0_decompress_bound, crunch64_yay0_decompress);
     run_tests("yaz0", ".Yaz0", crunch64_yaz0_compress_bound, crunch64_yaz0_compress, crunch64_yaz0_decompress_bound, crunch64_yaz0_decompress);
+    run_tests("yaz0 (aligned)", ".Yaz0", crunch64_yaz0_compress_bound, yaz0_compress_aligned, crunch64_yaz0_decompress_bound, crunch64_yaz0_decompress);
     run_tests("mio0", ".MIO0", crunch64_mio0_compress_bound, crunch64_mio0_compress, crunch64_mio0_decompress_bound, crunch64_mio0_decompress);
 
+    gzip_level = 1;
+    gzip_small_mem = false;
+    run_tests("gzip (level 1)", ".gzip-1", crunch64_gzip_compress_bound, gzip_compress, crunch64_gzip_decompress_bound, crunch64_gzip_decompress);
+
+    gzip_level = 2;
+    gzip_small_mem = false;
+    run_tests("gzip (level 2)", ".gzip-2", crunch64_gzip_compress_bound, gzip_compress, crunch64_gzip_decompress_bound, crunch64_gzip_decompress);
+
+    gzip_level = 3;
+    gzip_small_mem = false;
+    run_tests("gzip (level 3)", ".gzip-3", crunch64_gzip_compress_bound, gzip_compress, crunch64_gzip_decompress_bound, crunch64_gzip_decompress);
+
     gzip_level = 9;
     gzip_small_mem = false;
-    run_tests("gzip (level 9)", ".gzip-9", crunch64_gzip_compress_bound, gzip_compress, NULL, NULL);
+    run_tests("gzip (level 9)", ".gzip-9", crunch64_gzip_compress_bound, gzip_compress, crunch64_gzip_decompress_bound, crunch64_gzip_decompress);
 
     gzip_level = 9;
     gzip_small_mem = true;
-    run_tests("gzip (level 9, small_mem)", ".gzip-9-small-mem", crunch64_gzip_compress_bound, gzip_compress, NULL, NULL);
+    run_tests("gzip (level 9, small_mem)", ".gzip-9-small-mem", crunch64_gzip_compress_bound, gzip_compress, crunch64_gzip_decompress_bound, crunch64_gzip_decompress);
 
     gzip_level = 6;
     gzip_small_mem = true;
-    run_tests("gzip (level 6, small_mem)", ".gzip-6-small-mem", crunch64_gzip_compress_bound, gzip_compress, NULL, NULL);
+    run_tests("gzip (level 6, small_mem)", ".gzip-6-small-mem", crunch64_gzip_compress_bound, gzip_compress, crunch64_gzip_decompress_bound, crunch64_gzip_decompress);
+
+    run_tests("zlib (level 9)", ".zlib-9", crunch64_zlib_compress_bound, zlib_compress, crunch64_zlib_decompress_bound, crunch64_zlib_decompress);
+
+    run_tests("rare", ".rare", crunch64_rare_compress_bound, rare_compress, crunch64_rare_decompress_bound, crunch64_rare_decompress);
+
+    vpk0_two_sample = true;
+    run_tests("vpk0", ".vpk0", crunch64_vpk0_compress_bound, vpk0_compress, crunch64_vpk0_decompress_bound, crunch64_vpk0_decompress);
+
+    vpk0_two_sample = false;
+    run_tests("vpk0 (one-sample)", ".vpk0-one-sample", crunch64_vpk0_compress_bound, vpk0_compress, crunch64_vpk0_decompress_bound, crunch64_vpk0_decompress);
+
+    run_tests("lzkn64", ".lzkn64", crunch64_lzkn64_compress_bound, crunch64_lzkn64_compress, crunch64_lzkn64_decompress_bound, crunch64_lzkn64_decompress);
+
+    lz77_lz11 = false;
+    lz77_vram_safe = false;
+    run_tests("lz77 (lz10)", ".lz10", crunch64_lz77_compress_bound, lz77_compress, crunch64_lz77_decompress_bound, crunch64_lz77_decompress);
+
+    lz77_lz11 = false;
+    lz77_vram_safe = true;
+    run_tests("lz77 (lz10, vram-safe)", ".lz10-vram-safe", crunch64_lz77_compress_bound, lz77_compress, crunch64_lz77_decompress_bound, crunch64_lz77_decompress);
+
+    lz77_lz11 = true;
+    lz77_vram_safe = false;
+    run_tests("lz77 (lz11)", ".lz11", crunch64_lz77_compress_bound, lz77_compress, crunch64_lz77_decompress_bound, crunch64_lz77_decompress);
+
+    lz77_lz11 = true;
+    lz77_vram_safe = true;
+    run_tests("lz77 (lz11, vram-safe)", ".lz11-vram-safe", crunch64_lz77_compress_bound, lz77_compress, crunch64_lz77_decompress_bound, crunch64_lz77_decompress);
+
+    run_tests("rle", ".rle", crunch64_rle_compress_bound, crunch64_rle_compress, crunch64_rle_decompress_bound, crunch64_rle_decompress);
+
+    huffman_four_bit = true;
+    run_tests("huffman (4-bit)", ".huffman4", crunch64_huffman_compress_bound, huffman_compress, crunch64_huffman_decompress_bound, crunch64_huffman_decompress);
+
+    huffman_four_bit = false;
+    run_tests("huffman (8-bit)", ".huffman8", crunch64_huffman_compress_bound, huffman_compress, crunch64_huffman_decompress_bound, crunch64_huffman_decompress);
+
+    generic_format = Crunch64Format_Yaz0;
+    run_tests("yaz0 (generic)", ".Yaz0", generic_compress_bound, generic_compress, generic_decompress_bound, generic_decompress);
+
+    generic_format = Crunch64Format_Gzip;
+    run_tests("gzip (generic)", ".gzip-9", generic_compress_bound, generic_compress, generic_decompress_bound, generic_decompress);
+
+    generic_format = Crunch64Format_Zlib;
+    run_tests("zlib (generic)", ".zlib-9", generic_compress_bound, generic_compress, generic_decompress_bound, generic_decompress);
 
     if (errors == 0) {
         fprintf(stderr, "All tests passed\n");
@@ -0,0 +1,176 @@
+//! The `batch` command, which compresses many files in one process.
+//!
+//! The manifest lists the files to compress, either as TOML:
+//!
+//! ```toml
+//! [[files]]
+//! format = "yaz0"
+//! input = "assets/object.bin"
+//! output = "build/object.yaz0"
+//! options = { alignment = 128 }
+//! ```
+//!
+//! or as JSON, if its extension is `.json`:
+//!
+//! ```json
+//! { "files": [{ "format": "gzip", "input": "a.bin", "output": "a.gz", "options": { "level": 6 } }] }
+//! ```
+//!
+//! `options` takes the same options as the `compress` command and can be
+//! omitted. Relative paths are relative to the directory of the manifest.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use serde::Deserialize;
+
+use crate::{compress, error::Error, CompressArgs, CompressionType};
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    files: Vec<Entry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    format: CompressionType,
+    input: PathBuf,
+    output: PathBuf,
+    #[serde(default)]
+    options: CompressArgs,
+}
+
+enum Outcome {
+    Compressed,
+    UpToDate,
+}
+
+fn read_manifest(path: &Path) -> Result<Manifest, Error> {
+    let text = fs::read_to_string(path).map_err(|e| Error::io(path.display(), e))?;
+
+    let manifest = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(&text).map_err(|e| e.to_string())
+    };
+    manifest.map_err(|message| Error::Manifest {
+        path: path.display().to_string(),
+        message,
+    })
+}
+
+// Whether `output` was modified after both `input` and the manifest, so
+// compressing again would produce the same file. Editing the format or
+// options of an entry makes its output stale too.
+fn is_up_to_date(input: &Path, output: &Path, manifest: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+
+    match (modified(input), modified(manifest), modified(output)) {
+        (Ok(input), Ok(manifest), Ok(output)) => output >= input && output >= manifest,
+        _ => false,
+    }
+}
+
+fn process_entry(
+    entry: &Entry,
+    manifest_path: &Path,
+    base: &Path,
+    force: bool,
+) -> Result<Outcome, String> {
+    let input = base.join(&entry.input);
+    let output = base.join(&entry.output);
+
+    if !force && is_up_to_date(&input, &output, manifest_path) {
+        return Ok(Outcome::UpToDate);
+    }
+
+    let bytes = fs::read(&input).map_err(|e| format!("{}: {}", input.display(), e))?;
+    let compressed = compress(entry.format, &entry.options, &bytes)
+        .map_err(|e| format!("{}: {}", input.display(), e))?;
+
+    // The data is written to a temporary file that replaces the output once
+    // complete, so an interrupted build can't leave a truncated output that
+    // looks up to date
+    let mut temp_name = output.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".tmp");
+    let temp = output.with_file_name(temp_name);
+
+    let write = || -> io::Result<()> {
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&temp, compressed)?;
+        fs::rename(&temp, &output)
+    };
+    write().map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        format!("{}: {}", output.display(), e)
+    })?;
+
+    Ok(Outcome::Compressed)
+}
+
+/// Compresses every file in the manifest on `jobs` threads. Files that fail
+/// are reported and don't stop the others from being compressed.
+pub(crate) fn run(manifest_path: &Path, jobs: Option<usize>, force: bool) -> Result<(), Error> {
+    let manifest = read_manifest(manifest_path)?;
+    let base = manifest_path.parent().unwrap_or(Path::new(""));
+
+    let jobs = jobs
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .clamp(1, manifest.files.len().max(1));
+
+    let next_entry = AtomicUsize::new(0);
+    let compressed = AtomicUsize::new(0);
+    let up_to_date = AtomicUsize::new(0);
+    let errors = Mutex::new(Vec::new());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let index = next_entry.fetch_add(1, Ordering::Relaxed);
+                let Some(entry) = manifest.files.get(index) else {
+                    break;
+                };
+
+                match process_entry(entry, manifest_path, base, force) {
+                    Ok(Outcome::Compressed) => compressed.fetch_add(1, Ordering::Relaxed),
+                    Ok(Outcome::UpToDate) => up_to_date.fetch_add(1, Ordering::Relaxed),
+                    Err(e) => {
+                        errors.lock().unwrap().push((index, e));
+                        continue;
+                    }
+                };
+            });
+        }
+    });
+
+    // Report errors in manifest order, whichever thread found them
+    let mut errors = errors.into_inner().unwrap();
+    errors.sort();
+    for (_, e) in &errors {
+        eprintln!("error: {}", e);
+    }
+
+    println!(
+        "Compressed {} files, {} up to date, {} failed",
+        compressed.into_inner(),
+        up_to_date.into_inner(),
+        errors.len()
+    );
+
+    match errors.len() {
+        0 => Ok(()),
+        failed => Err(Error::Batch { failed }),
+    }
+}
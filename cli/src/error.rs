@@ -0,0 +1,58 @@
+use std::{fmt, io};
+
+use crunch64::Crunch64Error;
+
+/// An error that stops the CLI, reported on stderr before exiting with
+/// [`Error::exit_code`]
+#[derive(Debug)]
+pub(crate) enum Error {
+    /// The input could not be compressed or decompressed
+    Crunch64(Crunch64Error),
+    /// A file could not be read or written
+    Io { path: String, source: io::Error },
+    /// The batch manifest could not be parsed
+    Manifest { path: String, message: String },
+    /// Some of the files in a batch could not be compressed
+    Batch { failed: usize },
+    /// The verified data doesn't match the original
+    Mismatch,
+}
+
+impl Error {
+    pub(crate) fn io(path: impl fmt::Display, source: io::Error) -> Self {
+        Error::Io {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    /// Exit code for each kind of error. Clap already exits with 2 for
+    /// invalid arguments.
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            Error::Crunch64(_) => 1,
+            Error::Io { .. } => 3,
+            Error::Manifest { .. } => 4,
+            Error::Batch { .. } => 5,
+            Error::Mismatch => 6,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Crunch64(error) => write!(f, "{}", error),
+            Error::Io { path, source } => write!(f, "{}: {}", path, source),
+            Error::Manifest { path, message } => write!(f, "{}: {}", path, message),
+            Error::Batch { failed } => write!(f, "{} files could not be compressed", failed),
+            Error::Mismatch => write!(f, "output does not match the original"),
+        }
+    }
+}
+
+impl From<Crunch64Error> for Error {
+    fn from(error: Crunch64Error) -> Self {
+        Error::Crunch64(error)
+    }
+}
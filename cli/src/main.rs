@@ -1,110 +1,679 @@
-use clap::{Parser, ValueEnum};
-use crunch64::Crunch64Error;
+mod batch;
+mod error;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use crunch64::{scan::ScanOptions, Candidate, Codec, Crunch64Error, Encoder, Format};
+use error::Error;
 use std::{
-    fs::File,
-    io::{BufReader, BufWriter, Read, Write},
+    fs,
+    io::{self, Read, Write},
     path::PathBuf,
     process,
 };
 
-#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
-enum Command {
-    Compress,
-    Decompress,
-}
-
-#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(ValueEnum, serde::Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
 enum CompressionType {
     Yay0,
     Yaz0,
     Mio0,
     Gzip,
+    Zlib,
+    Deflate,
+    Rare,
+    Vpk0,
+    Lzkn64,
+    Lz77,
+    Rle,
+    Huffman,
+    /// Detect the format from the input file (decompression only)
+    Auto,
 }
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    #[arg()]
-    command: Command,
-    #[arg(ignore_case = true)]
-    format: CompressionType,
-    #[arg()]
-    in_path: String,
-    #[arg()]
-    out_path: String,
-    /// Compression level for gzip (4-9)
+impl CompressionType {
+    fn format(self) -> Option<Format> {
+        match self {
+            CompressionType::Yay0 => Some(Format::Yay0),
+            CompressionType::Yaz0 => Some(Format::Yaz0),
+            CompressionType::Mio0 => Some(Format::Mio0),
+            CompressionType::Gzip => Some(Format::Gzip),
+            CompressionType::Zlib => Some(Format::Zlib),
+            CompressionType::Deflate => Some(Format::Deflate),
+            CompressionType::Rare => Some(Format::Rare),
+            CompressionType::Vpk0 => Some(Format::Vpk0),
+            CompressionType::Lzkn64 => Some(Format::Lzkn64),
+            CompressionType::Lz77 => Some(Format::Lz77),
+            CompressionType::Rle => Some(Format::Rle),
+            CompressionType::Huffman => Some(Format::Huffman),
+            CompressionType::Auto => None,
+        }
+    }
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+enum ScanOutput {
+    /// One line per compressed file
+    #[default]
+    Text,
+    /// A JSON array of objects
+    Json,
+    /// Segments for a splat YAML configuration
+    Splat,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+enum VerifyOutput {
+    /// One line per check
+    #[default]
+    Text,
+    /// A JSON object
+    Json,
+}
+
+/// Compression options, given as command line arguments or in a batch manifest
+#[derive(clap::Args, serde::Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct CompressArgs {
+    /// Compression level for gzip, zlib, deflate and rare (1-9)
     #[arg(long, default_value_t = 9)]
     level: usize,
-    /// Output gzip blocks more frequently
+    /// Output DEFLATE blocks more frequently
     #[arg(long)]
     small_mem: bool,
+    /// Write a gzip header before the compressed data
+    #[arg(long)]
+    header: bool,
+    /// File name to store in the gzip header
+    #[arg(long, requires = "header")]
+    name: Option<String>,
+    /// Modification time to store in the gzip header, as a Unix timestamp
+    #[arg(long, requires = "header", default_value_t = 0)]
+    mtime: u32,
+    /// Data alignment to store in the Yaz0 header
+    #[arg(long, default_value_t = 0)]
+    alignment: u32,
+    /// Find the smallest Yaz0, Yay0 or MIO0 encoding instead of matching the original encoder
+    #[arg(long)]
+    optimal: bool,
+    /// Store vpk0 offsets as a single value instead of two
+    #[arg(long)]
+    one_sample: bool,
+    /// Use the LZ11 variant of lz77 instead of LZ10
+    #[arg(long)]
+    lz11: bool,
+    /// Don't copy from the previous byte in lz77 data, so that it can be decompressed to VRAM
+    #[arg(long)]
+    vram_safe: bool,
+    /// Encode huffman data as 4-bit symbols instead of bytes
+    #[arg(long)]
+    four_bit: bool,
 }
 
-fn compress(args: &Args, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
-    match args.format {
-        CompressionType::Yay0 => crunch64::yay0::compress(bytes),
-        CompressionType::Yaz0 => crunch64::yaz0::compress(bytes),
-        CompressionType::Mio0 => crunch64::mio0::compress(bytes),
-        CompressionType::Gzip => crunch64::gzip::compress(bytes, args.level, args.small_mem),
-        // _ => Err(Crunch64Error::UnsupportedCompressionType),
+impl Default for CompressArgs {
+    fn default() -> Self {
+        CompressArgs {
+            level: 9,
+            small_mem: false,
+            header: false,
+            name: None,
+            mtime: 0,
+            alignment: 0,
+            optimal: false,
+            one_sample: false,
+            lz11: false,
+            vram_safe: false,
+            four_bit: false,
+        }
     }
 }
 
-fn decompress(args: &Args, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
-    match args.format {
-        CompressionType::Yay0 => crunch64::yay0::decompress(bytes),
-        CompressionType::Yaz0 => crunch64::yaz0::decompress(bytes),
-        CompressionType::Mio0 => crunch64::mio0::decompress(bytes),
-        _ => Err(Crunch64Error::UnsupportedCompressionType),
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compress a file
+    Compress {
+        #[arg(ignore_case = true)]
+        format: CompressionType,
+        /// Input file, or `-` for stdin
+        in_path: String,
+        /// Output file, or `-` for stdout
+        out_path: String,
+        #[command(flatten)]
+        options: CompressArgs,
+    },
+    /// Decompress a file
+    Decompress {
+        #[arg(ignore_case = true)]
+        format: CompressionType,
+        /// Input file, or `-` for stdin
+        in_path: String,
+        /// Output file, or `-` for stdout
+        out_path: String,
+    },
+    /// Print the format and header of a compressed file
+    Info { in_path: String },
+    /// Find the compression settings that reproduce a compressed file
+    Identify { in_path: String },
+    /// Check that a compressed file decompresses and compresses back to the same bytes
+    Verify {
+        #[arg(ignore_case = true)]
+        format: CompressionType,
+        /// Compressed file, or `-` for stdin
+        compressed_path: String,
+        /// Original uncompressed file to compare the decompressed data with
+        uncompressed_path: Option<String>,
+        #[command(flatten)]
+        options: CompressArgs,
+        #[arg(long, value_enum, default_value_t = VerifyOutput::default())]
+        output: VerifyOutput,
+    },
+    /// Compress every file listed in a TOML or JSON manifest
+    Batch {
+        manifest_path: PathBuf,
+        /// Number of files to compress in parallel (defaults to the number of CPUs)
+        #[arg(long, short)]
+        jobs: Option<usize>,
+        /// Compress every file, even if its output is up to date
+        #[arg(long)]
+        force: bool,
+    },
+    /// List the compressed files embedded in a ROM image
+    Scan {
+        rom_path: String,
+        /// Only look for compressed files at offsets that are a multiple of this
+        #[arg(long, default_value_t = 1)]
+        alignment: usize,
+        /// Also look for headerless gzip and raw DEFLATE data (slower, may find false positives).
+        /// Raw DEFLATE data is only looked for with an alignment of 4 or more.
+        #[arg(long)]
+        headerless: bool,
+        #[arg(long, value_enum, default_value_t = ScanOutput::default())]
+        output: ScanOutput,
+    },
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+#[command(after_help = "\
+Exit codes:
+  0  Success
+  1  The input could not be compressed or decompressed
+  2  Invalid arguments
+  3  A file could not be read or written
+  4  The batch manifest is invalid
+  5  Some of the files in a batch failed
+  6  The verified file does not match")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+fn codec(format: Format, options: &CompressArgs) -> Box<dyn Codec> {
+    let (level, small_mem) = (options.level, options.small_mem);
+
+    match format {
+        Format::Gzip => {
+            let header = options.header.then(|| crunch64::gzip::GzipHeader {
+                filename: options.name.as_ref().map(|name| name.as_bytes().to_vec()),
+                mtime: options.mtime,
+                ..Default::default()
+            });
+            Box::new(crunch64::gzip::Gzip {
+                options: crunch64::gzip::CompressOptions {
+                    level,
+                    small_mem,
+                    header,
+                },
+            })
+        }
+        Format::Yaz0 => Box::new(crunch64::yaz0::Yaz0 {
+            options: crunch64::yaz0::CompressOptions {
+                alignment: options.alignment,
+                optimal: options.optimal,
+            },
+        }),
+        Format::Yay0 => Box::new(crunch64::yay0::Yay0 {
+            options: crunch64::yay0::CompressOptions {
+                optimal: options.optimal,
+            },
+        }),
+        Format::Mio0 => Box::new(crunch64::mio0::Mio0 {
+            options: crunch64::mio0::CompressOptions {
+                optimal: options.optimal,
+            },
+        }),
+        Format::Zlib => Box::new(crunch64::zlib::Zlib { level, small_mem }),
+        Format::Deflate => Box::new(crunch64::gzip::Deflate { level, small_mem }),
+        Format::Rare => Box::new(crunch64::rare::Rare { level, small_mem }),
+        Format::Vpk0 => Box::new(crunch64::vpk0::Vpk0 {
+            options: crunch64::vpk0::CompressOptions {
+                method: if options.one_sample {
+                    crunch64::vpk0::Method::OneSample
+                } else {
+                    crunch64::vpk0::Method::TwoSample
+                },
+            },
+        }),
+        Format::Lzkn64 => Box::new(crunch64::lzkn64::Lzkn64::DEFAULT),
+        Format::Lz77 => Box::new(crunch64::lz77::Lz77 {
+            options: crunch64::lz77::CompressOptions {
+                variant: if options.lz11 {
+                    crunch64::lz77::Variant::Lz11
+                } else {
+                    crunch64::lz77::Variant::Lz10
+                },
+                vram_safe: options.vram_safe,
+            },
+        }),
+        Format::Rle => Box::new(crunch64::rle::Rle::DEFAULT),
+        Format::Huffman => Box::new(crunch64::huffman::Huffman {
+            options: crunch64::huffman::CompressOptions {
+                data_size: if options.four_bit {
+                    crunch64::huffman::DataSize::Four
+                } else {
+                    crunch64::huffman::DataSize::Eight
+                },
+            },
+        }),
     }
 }
 
-fn main() {
-    let args = Args::parse();
+fn compress(
+    format: CompressionType,
+    options: &CompressArgs,
+    bytes: &[u8],
+) -> Result<Box<[u8]>, Crunch64Error> {
+    // The format can only be detected from compressed data
+    let format = format
+        .format()
+        .ok_or(Crunch64Error::UnsupportedCompressionType)?;
+
+    codec(format, options).compress(bytes)
+}
+
+fn decompression_format(format: CompressionType, bytes: &[u8]) -> Result<Format, Crunch64Error> {
+    format
+        .format()
+        .or_else(|| crunch64::detect(bytes))
+        .ok_or(Crunch64Error::UnsupportedCompressionType)
+}
+
+fn decompress(format: CompressionType, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    decompression_format(format, bytes)?.decompress(bytes)
+}
+
+fn print_info(bytes: &[u8]) -> Result<(), Crunch64Error> {
+    let format = crunch64::detect(bytes).ok_or(Crunch64Error::UnsupportedCompressionType)?;
+
+    println!("Format: {}", format);
+    println!("Compressed size: 0x{:X}", bytes.len());
+    println!("Header size: 0x{:X}", format.header_size(bytes)?);
 
-    let file_bytes = read_file_bytes(&args.in_path);
+    match format {
+        Format::Yay0 => {
+            let header = crunch64::yay0::Yay0Header::parse(bytes)?;
+            println!("Uncompressed size: 0x{:X}", header.uncompressed_size);
+            println!("Link table offset: 0x{:X}", header.link_table_offset);
+            println!("Chunk offset: 0x{:X}", header.chunk_offset);
+        }
+        Format::Yaz0 => {
+            let header = crunch64::yaz0::Yaz0Header::parse_lenient(bytes)?;
+            println!("Uncompressed size: 0x{:X}", header.uncompressed_size);
+            println!("Alignment: 0x{:X}", header.alignment);
+        }
+        Format::Mio0 => {
+            let header = crunch64::mio0::Mio0Header::parse(bytes)?;
+            println!("Uncompressed size: 0x{:X}", header.uncompressed_size);
+            println!("Link table offset: 0x{:X}", header.link_table_offset);
+            println!("Chunk offset: 0x{:X}", header.chunk_offset);
+        }
+        Format::Gzip => {
+            let (_, header) = crunch64::gzip::decompress_with_header(bytes)?;
+            println!("Uncompressed size: 0x{:X}", format.decompress_bound(bytes)?);
+            if let Some(header) = header {
+                if let Some(filename) = &header.filename {
+                    println!("File name: {}", String::from_utf8_lossy(filename));
+                }
+                if let Some(comment) = &header.comment {
+                    println!("Comment: {}", String::from_utf8_lossy(comment));
+                }
+                println!("Modification time: {}", header.mtime);
+                println!("Extra flags: {}", header.xfl);
+                println!("OS: {}", header.os);
+            }
+        }
+        Format::Vpk0 => {
+            let header = crunch64::vpk0::Vpk0Header::parse(bytes)?;
+            println!("Uncompressed size: 0x{:X}", header.uncompressed_size);
+            println!("Method: {}", header.method.name());
+        }
+        _ => println!("Uncompressed size: 0x{:X}", format.decompress_bound(bytes)?),
+    }
 
-    let out_bytes = match args.command {
-        Command::Compress => match compress(&args, file_bytes.as_slice()) {
-            Ok(bytes) => bytes,
-            Err(error) => {
-                eprintln!("{:?}", error);
-                process::exit(1);
+    Ok(())
+}
+
+// The `compress` arguments that select the settings of `encoder`
+fn compress_args(encoder: &Encoder) -> String {
+    let mut args = encoder.format().to_string();
+
+    let (level, small_mem) = match encoder {
+        Encoder::Yay0(_) | Encoder::Mio0(_) | Encoder::Lzkn64(_) | Encoder::Rle(_) => return args,
+        Encoder::Yaz0(codec) => {
+            if codec.options.alignment != 0 {
+                args += &format!(" --alignment {}", codec.options.alignment);
+            }
+            return args;
+        }
+        Encoder::Gzip(codec) => {
+            if let Some(header) = &codec.options.header {
+                args += " --header";
+                if let Some(filename) = &header.filename {
+                    args += &format!(" --name {:?}", String::from_utf8_lossy(filename));
+                }
+                args += &format!(" --mtime {}", header.mtime);
+            }
+            (codec.options.level, codec.options.small_mem)
+        }
+        Encoder::Zlib(codec) => (codec.level, codec.small_mem),
+        Encoder::Deflate(codec) => (codec.level, codec.small_mem),
+        Encoder::Rare(codec) => (codec.level, codec.small_mem),
+        Encoder::Vpk0(codec) => {
+            if codec.options.method == crunch64::vpk0::Method::OneSample {
+                args += " --one-sample";
+            }
+            return args;
+        }
+        Encoder::Lz77(codec) => {
+            if codec.options.variant == crunch64::lz77::Variant::Lz11 {
+                args += " --lz11";
+            }
+            if codec.options.vram_safe {
+                args += " --vram-safe";
             }
-        },
-        Command::Decompress => match decompress(&args, file_bytes.as_slice()) {
-            Ok(bytes) => bytes,
-            Err(error) => {
-                eprintln!("{:?}", error);
-                process::exit(1);
+            return args;
+        }
+        Encoder::Huffman(codec) => {
+            if codec.options.data_size == crunch64::huffman::DataSize::Four {
+                args += " --four-bit";
             }
-        },
+            return args;
+        }
     };
 
-    let mut buf_writer = match File::create(args.out_path) {
-        Ok(file) => BufWriter::new(file),
-        Err(_error) => {
-            eprintln!("Failed to create file");
-            process::exit(1);
+    args += &format!(" --level {}", level);
+    if small_mem {
+        args += " --small-mem";
+    }
+    args
+}
+
+fn print_identify(bytes: &[u8]) -> Result<(), Crunch64Error> {
+    const MAX_CLOSEST: usize = 5;
+
+    let candidates = crunch64::identify(bytes)?;
+    let matches: Vec<&Candidate> = candidates.iter().filter(|c| c.is_match()).collect();
+
+    if !matches.is_empty() {
+        println!("Matching settings:");
+        for candidate in matches {
+            println!("  crunch64 compress {}", compress_args(&candidate.encoder));
         }
-    };
+    } else {
+        println!("No settings reproduce the input exactly. Closest:");
+        for candidate in candidates.iter().take(MAX_CLOSEST) {
+            println!(
+                "  crunch64 compress {} (first difference at 0x{:X})",
+                compress_args(&candidate.encoder),
+                candidate.first_difference.unwrap_or_default()
+            );
+        }
+    }
 
-    let _ = buf_writer.write_all(&out_bytes);
+    Ok(())
 }
 
-pub fn read_file_bytes<P: Into<PathBuf>>(path: P) -> Vec<u8> {
-    let file = match File::open(path.into()) {
-        Ok(file) => file,
-        Err(_error) => {
-            eprintln!("Failed to open file");
-            process::exit(1);
+/// Result of comparing some output with the bytes it should reproduce
+#[derive(serde::Serialize)]
+struct Comparison {
+    #[serde(rename = "match")]
+    matches: bool,
+    size: usize,
+    expected_size: usize,
+    first_difference: Option<usize>,
+}
+
+impl Comparison {
+    // Zero bytes after the end of the output only count as a difference if
+    // `padding` is false
+    fn new(output: &[u8], expected: &[u8], padding: bool) -> Self {
+        let common = output
+            .iter()
+            .zip(expected)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let padded = padding && expected[common..].iter().all(|&b| b == 0);
+        let matches = common == output.len() && (common == expected.len() || padded);
+
+        Comparison {
+            matches,
+            size: output.len(),
+            expected_size: expected.len(),
+            first_difference: if matches { None } else { Some(common) },
         }
-    };
+    }
+
+    fn print_text(&self, name: &str) {
+        match self.first_difference {
+            None => println!("{}: match (0x{:X} bytes)", name, self.size),
+            Some(offset) => println!(
+                "{}: mismatch at 0x{:X} (0x{:X} bytes, expected 0x{:X})",
+                name, offset, self.size, self.expected_size
+            ),
+        }
+    }
+}
+
+/// JSON output of `verify`
+#[derive(serde::Serialize)]
+struct VerifyReport<'a> {
+    format: String,
+    decompression: Option<&'a Comparison>,
+    compression: &'a Comparison,
+}
 
-    let mut buf_reader = BufReader::new(file);
-    let mut buffer = Vec::new();
+fn verify(
+    format: CompressionType,
+    options: &CompressArgs,
+    compressed: &[u8],
+    uncompressed: Option<&[u8]>,
+    output: VerifyOutput,
+) -> Result<(), Error> {
+    let format = decompression_format(format, compressed)?;
+    let decompressed = format.decompress(compressed)?;
+    let recompressed = codec(format, options).compress(&decompressed)?;
 
-    let _ = buf_reader.read_to_end(&mut buffer);
+    let decompression =
+        uncompressed.map(|original| Comparison::new(&decompressed, original, false));
+    let compression = Comparison::new(&recompressed, compressed, true);
 
-    buffer
+    match output {
+        VerifyOutput::Text => {
+            println!("Format: {}", format);
+            if let Some(decompression) = &decompression {
+                decompression.print_text("Decompression");
+            }
+            compression.print_text("Compression");
+        }
+        VerifyOutput::Json => {
+            let report = VerifyReport {
+                format: format.to_string(),
+                decompression: decompression.as_ref(),
+                compression: &compression,
+            };
+            println!("{}", serde_json::to_string(&report).unwrap());
+        }
+    }
+
+    let mismatches = decompression
+        .iter()
+        .chain([&compression])
+        .any(|comparison| !comparison.matches);
+    if mismatches {
+        Err(Error::Mismatch)
+    } else {
+        Ok(())
+    }
+}
+
+/// JSON output of `scan`, one per compressed file
+#[derive(serde::Serialize)]
+struct ScanEntry {
+    offset: usize,
+    format: String,
+    compressed_size: usize,
+    decompressed_size: usize,
+}
+
+fn print_scan(rom: &[u8], options: &ScanOptions, output: ScanOutput) {
+    let blobs = crunch64::scan::scan_with_options(rom, options);
+
+    match output {
+        ScanOutput::Text => {
+            println!("Offset     Format  Compressed  Decompressed");
+            for blob in &blobs {
+                println!(
+                    "0x{:08X} {:<7} 0x{:<9X} 0x{:X}",
+                    blob.offset, blob.format, blob.compressed_size, blob.decompressed_size
+                );
+            }
+        }
+        ScanOutput::Json => {
+            let entries: Vec<ScanEntry> = blobs
+                .iter()
+                .map(|blob| ScanEntry {
+                    offset: blob.offset,
+                    format: blob.format.to_string(),
+                    compressed_size: blob.compressed_size,
+                    decompressed_size: blob.decompressed_size,
+                })
+                .collect();
+            println!("{}", serde_json::to_string(&entries).unwrap());
+        }
+        ScanOutput::Splat => {
+            // splat handles Yay0 and MIO0 segments itself, the other formats
+            // are split as binary files. Gaps between compressed files are
+            // binary segments too, so the list covers the whole ROM.
+            let mut end = 0;
+            for blob in &blobs {
+                if blob.offset > end {
+                    println!("- [0x{:X}, bin]", end);
+                }
+                match blob.format {
+                    Format::Yay0 | Format::Mio0 => {
+                        println!("- [0x{:X}, {}]", blob.offset, blob.format)
+                    }
+                    format => println!(
+                        "- [0x{:X}, bin] # {}, decompressed size 0x{:X}",
+                        blob.offset, format, blob.decompressed_size
+                    ),
+                }
+                end = blob.offset + blob.compressed_size;
+            }
+            if rom.len() > end {
+                println!("- [0x{:X}, bin]", end);
+            }
+            println!("- [0x{:X}]", rom.len());
+        }
+    }
+}
+
+fn read_input(path: &str) -> Result<Vec<u8>, Error> {
+    if path == "-" {
+        let mut buffer = Vec::new();
+        io::stdin()
+            .read_to_end(&mut buffer)
+            .map_err(|e| Error::io("<stdin>", e))?;
+        Ok(buffer)
+    } else {
+        fs::read(path).map_err(|e| Error::io(path, e))
+    }
+}
+
+fn write_output(path: &str, bytes: &[u8]) -> Result<(), Error> {
+    if path == "-" {
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(bytes)
+            .and_then(|()| stdout.flush())
+            .map_err(|e| Error::io("<stdout>", e))
+    } else {
+        fs::write(path, bytes).map_err(|e| Error::io(path, e))
+    }
+}
+
+fn run(command: &Command) -> Result<(), Error> {
+    match command {
+        Command::Compress {
+            format,
+            in_path,
+            out_path,
+            options,
+        } => {
+            let bytes = compress(*format, options, &read_input(in_path)?)?;
+            write_output(out_path, &bytes)
+        }
+        Command::Decompress {
+            format,
+            in_path,
+            out_path,
+        } => {
+            let bytes = decompress(*format, &read_input(in_path)?)?;
+            write_output(out_path, &bytes)
+        }
+        Command::Info { in_path } => Ok(print_info(&read_input(in_path)?)?),
+        Command::Identify { in_path } => Ok(print_identify(&read_input(in_path)?)?),
+        Command::Verify {
+            format,
+            compressed_path,
+            uncompressed_path,
+            options,
+            output,
+        } => {
+            let uncompressed = uncompressed_path.as_deref().map(read_input).transpose()?;
+            verify(
+                *format,
+                options,
+                &read_input(compressed_path)?,
+                uncompressed.as_deref(),
+                *output,
+            )
+        }
+        Command::Batch {
+            manifest_path,
+            jobs,
+            force,
+        } => batch::run(manifest_path, *jobs, *force),
+        Command::Scan {
+            rom_path,
+            alignment,
+            headerless,
+            output,
+        } => {
+            let options = ScanOptions {
+                alignment: *alignment,
+                headerless: *headerless,
+            };
+            print_scan(&read_input(rom_path)?, &options, *output);
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(error) = run(&args.command) {
+        eprintln!("error: {}", error);
+        process::exit(error.exit_code());
+    }
 }
@@ -0,0 +1,323 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    time::Duration,
+};
+
+fn test_data(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../test_data")
+        .join(name)
+}
+
+// An empty directory for the files written by one test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn crunch64(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_crunch64"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_compress_file() {
+    let dir = temp_dir("compress_file");
+    let out_path = dir.join("small.txt.Yaz0");
+
+    let output = crunch64(
+        &[
+            "compress",
+            "yaz0",
+            test_data("small.txt").to_str().unwrap(),
+            out_path.to_str().unwrap(),
+        ],
+        &[],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fs::read(out_path).unwrap(),
+        fs::read(test_data("small.txt.Yaz0")).unwrap()
+    );
+}
+
+#[test]
+fn test_stdin_stdout() {
+    let original = fs::read(test_data("small.txt")).unwrap();
+
+    let compressed = crunch64(&["compress", "mio0", "-", "-"], &original);
+    assert!(compressed.status.success(), "{}", stderr(&compressed));
+    assert_eq!(
+        compressed.stdout,
+        fs::read(test_data("small.txt.MIO0")).unwrap()
+    );
+
+    let decompressed = crunch64(&["decompress", "auto", "-", "-"], &compressed.stdout);
+    assert!(decompressed.status.success(), "{}", stderr(&decompressed));
+    assert_eq!(decompressed.stdout, original);
+}
+
+#[test]
+fn test_missing_input() {
+    let dir = temp_dir("missing_input");
+    let in_path = dir.join("missing.bin");
+
+    let output = crunch64(&["decompress", "yaz0", in_path.to_str().unwrap(), "-"], &[]);
+
+    assert_eq!(output.status.code(), Some(3));
+    let message = stderr(&output);
+    assert!(message.contains(in_path.to_str().unwrap()), "{}", message);
+    assert!(message.contains("os error"), "{}", message);
+}
+
+#[test]
+fn test_invalid_data() {
+    let output = crunch64(&["decompress", "yaz0", "-", "-"], b"not compressed");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "error: File does not begin with Yaz0 header\n"
+    );
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_invalid_arguments() {
+    let output = crunch64(&["compress", "unknown", "-", "-"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_batch() {
+    let dir = temp_dir("batch");
+    fs::copy(test_data("small.txt"), dir.join("small.txt")).unwrap();
+    fs::write(
+        dir.join("manifest.toml"),
+        r#"
+[[files]]
+format = "yay0"
+input = "small.txt"
+output = "out/small.txt.Yay0"
+
+[[files]]
+format = "gzip"
+input = "small.txt"
+output = "out/small.txt.gzip-6-small-mem"
+options = { level = 6, small_mem = true }
+"#,
+    )
+    .unwrap();
+    let manifest = dir.join("manifest.toml");
+
+    let output = crunch64(&["batch", manifest.to_str().unwrap()], &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Compressed 2 files, 0 up to date, 0 failed\n"
+    );
+    for name in ["small.txt.Yay0", "small.txt.gzip-6-small-mem"] {
+        assert_eq!(
+            fs::read(dir.join("out").join(name)).unwrap(),
+            fs::read(test_data(name)).unwrap()
+        );
+    }
+    // No temporary files are left behind
+    assert_eq!(fs::read_dir(dir.join("out")).unwrap().count(), 2);
+
+    let output = crunch64(&["batch", manifest.to_str().unwrap()], &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Compressed 0 files, 2 up to date, 0 failed\n"
+    );
+}
+
+#[test]
+fn test_batch_manifest_changed() {
+    let dir = temp_dir("batch_manifest_changed");
+    fs::copy(test_data("small.txt"), dir.join("small.txt")).unwrap();
+    let manifest = dir.join("manifest.toml");
+    let write_manifest = |level: usize| {
+        fs::write(
+            &manifest,
+            format!(
+                "[[files]]\nformat = \"gzip\"\ninput = \"small.txt\"\noutput = \"small.txt.gz\"\noptions = {{ level = {level} }}\n"
+            ),
+        )
+        .unwrap();
+    };
+
+    write_manifest(9);
+    let output = crunch64(&["batch", manifest.to_str().unwrap()], &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    // Outputs older than the manifest are compressed again with the new options
+    write_manifest(1);
+    let later = fs::metadata(dir.join("small.txt.gz"))
+        .unwrap()
+        .modified()
+        .unwrap()
+        + Duration::from_secs(1);
+    fs::File::options()
+        .write(true)
+        .open(&manifest)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+
+    let output = crunch64(&["batch", manifest.to_str().unwrap()], &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Compressed 1 files, 0 up to date, 0 failed\n"
+    );
+    assert_eq!(
+        fs::read(dir.join("small.txt.gz")).unwrap(),
+        fs::read(test_data("small.txt.gzip-1")).unwrap()
+    );
+}
+
+#[test]
+fn test_batch_json() {
+    let dir = temp_dir("batch_json");
+    fs::copy(test_data("small.txt"), dir.join("small.txt")).unwrap();
+    fs::write(
+        dir.join("manifest.json"),
+        r#"{ "files": [{ "format": "yaz0", "input": "small.txt", "output": "small.txt.Yaz0" }] }"#,
+    )
+    .unwrap();
+
+    let output = crunch64(&["batch", dir.join("manifest.json").to_str().unwrap()], &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fs::read(dir.join("small.txt.Yaz0")).unwrap(),
+        fs::read(test_data("small.txt.Yaz0")).unwrap()
+    );
+}
+
+#[test]
+fn test_batch_errors() {
+    let dir = temp_dir("batch_errors");
+    let manifest = dir.join("manifest.toml");
+
+    fs::write(&manifest, "[[files]]\nformat = \"lz77\"\n").unwrap();
+    let output = crunch64(&["batch", manifest.to_str().unwrap()], &[]);
+    assert_eq!(output.status.code(), Some(4));
+
+    fs::write(
+        &manifest,
+        "[[files]]\nformat = \"yaz0\"\ninput = \"missing.bin\"\noutput = \"out.bin\"\n",
+    )
+    .unwrap();
+    let output = crunch64(&["batch", manifest.to_str().unwrap()], &[]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(
+        stderr(&output).contains("missing.bin"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn test_verify() {
+    let output = crunch64(
+        &[
+            "verify",
+            "yaz0",
+            test_data("small.txt.Yaz0").to_str().unwrap(),
+            test_data("small.txt").to_str().unwrap(),
+        ],
+        &[],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Format: yaz0\nDecompression: match (0x78 bytes)\nCompression: match (0x6E bytes)\n"
+    );
+}
+
+#[test]
+fn test_verify_mismatch() {
+    let output = crunch64(
+        &[
+            "compress",
+            "yaz0",
+            test_data("mips_gist_wiseguy_yaz0.bin").to_str().unwrap(),
+            "-",
+            "--optimal",
+        ],
+        &[],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let compressed = output.stdout;
+
+    let output = crunch64(&["verify", "auto", "-", "--output", "json"], &compressed);
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap(),
+        serde_json::json!({
+            "format": "yaz0",
+            "decompression": null,
+            "compression": {
+                "match": false,
+                "size": 4326,
+                "expected_size": 4314,
+                "first_difference": 45,
+            },
+        })
+    );
+
+    let output = crunch64(&["verify", "yaz0", "-", "--optimal"], &compressed);
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn test_scan_json() {
+    let dir = temp_dir("scan_json");
+    let compressed = fs::read(test_data("small.txt.Yay0")).unwrap();
+    let mut rom = vec![0; 0x100];
+    rom.extend(&compressed);
+    rom.extend([0; 0x100]);
+    fs::write(dir.join("rom.bin"), rom).unwrap();
+
+    let output = crunch64(
+        &[
+            "scan",
+            dir.join("rom.bin").to_str().unwrap(),
+            "--output",
+            "json",
+        ],
+        &[],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap(),
+        serde_json::json!([{
+            "offset": 0x100,
+            "format": "yay0",
+            "compressed_size": compressed.len(),
+            "decompressed_size": fs::read(test_data("small.txt")).unwrap().len(),
+        }])
+    );
+}
@@ -0,0 +1,16 @@
+#![no_main]
+
+use crunch64::{hal_lz, Codec, Format};
+use libfuzzer_sys::fuzz_target;
+
+// Decompressing arbitrary data must return an error instead of panicking
+fuzz_target!(|data: &[u8]| {
+    for format in Format::ALL {
+        let _ = format.header_size(data);
+        let _ = format.decompress_bound(data);
+        let _ = format.decompress(data);
+    }
+
+    let _ = hal_lz::HalLz::DEFAULT.decompress(data);
+    let _ = crunch64::detect(data);
+});
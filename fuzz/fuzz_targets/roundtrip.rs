@@ -0,0 +1,64 @@
+#![no_main]
+
+use crunch64::{hal_lz, huffman, lz77, mio0, vpk0, yay0, yaz0, Codec, Format};
+use libfuzzer_sys::fuzz_target;
+
+fn check_roundtrip(codec: &dyn Codec, data: &[u8]) {
+    let compressed = codec.compress(data).unwrap();
+    assert!(compressed.len() <= codec.compress_bound(data.len()).unwrap());
+    assert_eq!(codec.decompress(&compressed).unwrap().as_ref(), data);
+}
+
+// Compressing any data must succeed and decompress back to the same data
+fuzz_target!(|data: &[u8]| {
+    for format in Format::ALL {
+        check_roundtrip(&format, data);
+    }
+
+    let others: [&dyn Codec; 9] = [
+        &yaz0::Yaz0 {
+            options: yaz0::CompressOptions {
+                optimal: true,
+                ..Default::default()
+            },
+        },
+        &yay0::Yay0 {
+            options: yay0::CompressOptions { optimal: true },
+        },
+        &mio0::Mio0 {
+            options: mio0::CompressOptions { optimal: true },
+        },
+        &vpk0::Vpk0 {
+            options: vpk0::CompressOptions {
+                method: vpk0::Method::OneSample,
+            },
+        },
+        &lz77::Lz77 {
+            options: lz77::CompressOptions {
+                variant: lz77::Variant::Lz11,
+                vram_safe: false,
+            },
+        },
+        &lz77::Lz77 {
+            options: lz77::CompressOptions {
+                variant: lz77::Variant::Lz10,
+                vram_safe: true,
+            },
+        },
+        &lz77::Lz77 {
+            options: lz77::CompressOptions {
+                variant: lz77::Variant::Lz11,
+                vram_safe: true,
+            },
+        },
+        &huffman::Huffman {
+            options: huffman::CompressOptions {
+                data_size: huffman::DataSize::Four,
+            },
+        },
+        &hal_lz::HalLz::DEFAULT,
+    ];
+    for codec in others {
+        check_roundtrip(codec, data);
+    }
+});
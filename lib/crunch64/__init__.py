@@ -7,7 +7,18 @@ __version_info__ = (0, 6, 0)
 __version__ = ".".join(map(str, __version_info__))
 __author__ = "decompals"
 
+from .crunch64 import compress as compress
+from .crunch64 import decompress as decompress
+from .crunch64 import detect as detect
+
 from . import yay0 as yay0
 from . import yaz0 as yaz0
 from . import mio0 as mio0
 from . import gzip as gzip
+from . import zlib as zlib
+from . import rare as rare
+from . import vpk0 as vpk0
+from . import lzkn64 as lzkn64
+from . import lz77 as lz77
+from . import rle as rle
+from . import huffman as huffman
@@ -6,3 +6,14 @@ from . import yay0 as yay0
 from . import yaz0 as yaz0
 from . import mio0 as mio0
 from . import gzip as gzip
+from . import zlib as zlib
+from . import rare as rare
+from . import vpk0 as vpk0
+from . import lzkn64 as lzkn64
+from . import lz77 as lz77
+from . import rle as rle
+from . import huffman as huffman
+
+def decompress(format: str, data: bytes) -> bytes: ...
+def compress(format: str, data: bytes) -> bytes: ...
+def detect(data: bytes) -> str | None: ...
@@ -2,4 +2,7 @@
 
 from __future__ import annotations
 
+from .crunch64 import decompress_gzip as decompress
 from .crunch64 import compress_gzip as compress
+from .crunch64 import decompress_gzip_raw as decompress_raw
+from .crunch64 import compress_gzip_raw as compress_raw
@@ -2,4 +2,14 @@
 
 from __future__ import annotations
 
-def compress(data: bytes, level: int = 9, small_mem: bool = False) -> bytes: ...
+def decompress(data: bytes) -> bytes: ...
+def decompress_raw(data: bytes) -> bytes: ...
+def compress_raw(data: bytes, level: int = 9, small_mem: bool = False) -> bytes: ...
+def compress(
+    data: bytes,
+    level: int = 9,
+    small_mem: bool = False,
+    header: bool = False,
+    name: str | None = None,
+    mtime: int = 0,
+) -> bytes: ...
@@ -0,0 +1,6 @@
+#!/usr/bin/env python3
+
+from __future__ import annotations
+
+from .crunch64 import decompress_lz77 as decompress
+from .crunch64 import compress_lz77 as compress
@@ -3,4 +3,4 @@
 from __future__ import annotations
 
 def decompress(data: bytes) -> bytes: ...
-def compress(data: bytes) -> bytes: ...
+def compress(data: bytes, optimal: bool = False) -> bytes: ...
@@ -0,0 +1,6 @@
+#!/usr/bin/env python3
+
+from __future__ import annotations
+
+from .crunch64 import decompress_vpk0 as decompress
+from .crunch64 import compress_vpk0 as compress
@@ -1,14 +1,18 @@
 // Implements matching zlib/DEFLATE compression for old gzip versions (before
 // 2006 or so), used by some N64 and iQue games. The compressed output has a
 // gzip footer (with a CRC32 checksum and the uncompressed size) but the gzip
-// header is omitted. See https://github.com/Thar0/gzip-1.3.3-ique for the
-// original gzip code and https://datatracker.ietf.org/doc/html/rfc1951 for
-// details on the DEFLATE compression format.
+// header is omitted. Decompression accepts both this format and regular gzip
+// files. See https://github.com/Thar0/gzip-1.3.3-ique for the original gzip
+// code and https://datatracker.ietf.org/doc/html/rfc1951 for details on the
+// DEFLATE compression format.
 
 use alloc::{boxed::Box, vec::Vec};
 use core::cmp;
 
-use crate::{utils, Crunch64Error};
+use crate::{
+    utils::{self, Output, SliceOutput},
+    Codec, Crunch64Error, Format,
+};
 
 // Bitstream writer for compressed output
 struct OutputStream {
@@ -49,9 +53,9 @@ impl OutputStream {
         self.bytes.extend_from_slice(bytes);
     }
 
-    fn into_boxed_slice(mut self) -> Box<[u8]> {
+    fn into_vec(mut self) -> Vec<u8> {
         self.flush_bits();
-        self.bytes.into_boxed_slice()
+        self.bytes
     }
 }
 
@@ -584,6 +588,8 @@ const FIXED_DCODE_LENGTH_COUNTS: [u16; 5] = [0, 0, 0, 0, 32];
 struct BlockWriter {
     // Maximum number of elements in a block
     buffer_size: usize,
+    // Compression level, blocks are only ended early for levels 3 and up
+    level: usize,
     // Elements for the current block
     code_elements: Vec<CodeElement>,
     data_elements: Vec<DataElement>,
@@ -599,9 +605,10 @@ struct BlockWriter {
 }
 
 impl BlockWriter {
-    fn new(buffer_size: usize) -> BlockWriter {
+    fn new(buffer_size: usize, level: usize) -> BlockWriter {
         BlockWriter {
             buffer_size,
+            level,
             code_elements: Vec::with_capacity(L_NUM_SYMBOLS + D_NUM_SYMBOLS),
             data_elements: Vec::with_capacity(buffer_size),
             num_matches: 0,
@@ -681,7 +688,7 @@ impl BlockWriter {
         if num_elements == self.buffer_size - 1 {
             return true;
         }
-        if num_elements % 0x1000 == 0 {
+        if self.level > 2 && num_elements % 0x1000 == 0 {
             let mut estimated_output_size_bits = num_elements * 8;
             for (i, &freq) in self.dfreqs.iter().enumerate() {
                 estimated_output_size_bits += (5 + D_EXTRA_BITS[i] as usize) * freq as usize;
@@ -866,257 +873,1160 @@ fn update_hash(hash: usize, c: u8, mask: usize) -> usize {
     ((hash << 5) ^ c as usize) & mask
 }
 
-fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
-    // Upper bound based on fixed-Huffman-code blocks consisting of only 9-byte
+pub(crate) fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
+    // Upper bound based on fixed-Huffman-code blocks consisting of only 9-bit
     // literals (stored blocks might be shorter but might fall out of the window
     // before we can emit them). The minimum block size is 0x1000 bytes (if
-    // `should_flush_block` decides to end a block early) and each block
-    // requires a 3-bit header.
-    let upper_bound_bits = 3 * input_size.div_ceil(0x1000)// block headers
-        + 9 * input_size // literals
-        + 8 * 8; // footer
-    Ok(upper_bound_bits.div_ceil(8))
+    // `should_flush_block` decides to end a block early), there is always at
+    // least one block, and each block requires a 3-bit header and a 7-bit
+    // end-of-block code.
+    let block_count = cmp::max(input_size.div_ceil(0x1000), 1);
+    let upper_bound_bits = (3 + 7) * block_count // block headers and ends
+        + 9 * input_size; // literals
+    Ok(upper_bound_bits.div_ceil(8) + 8) // footer
 }
 
-pub fn compress(bytes: &[u8], level: usize, small_mem: bool) -> Result<Box<[u8]>, Crunch64Error> {
-    let input_size = bytes.len();
-
-    // Levels 0-3 use a slightly different compression algorithm which is not
-    // implemented here
-    if !(4..=9).contains(&level) {
-        return Err(Crunch64Error::InvalidCompressionLevel);
-    }
-
-    let config = &COMPRESSION_LEVELS[level];
-    let buffer_size = if small_mem { 0x2000 } else { 0x8000 };
-    let hash_bits = if small_mem { 13 } else { 15 };
-    let hash_size = 1 << hash_bits;
-    let hash_mask = hash_size - 1;
-
-    let mut output = OutputStream::new(size_for_compressed_buffer(bytes.len())?);
-    let mut writer = BlockWriter::new(buffer_size);
-    let mut hasher = crc32fast::Hasher::new();
-
-    // Old gzip versions can read past the window into memory used for other
-    // global variables, which can affect compression output. We allocate a
-    // little extra space and reproduce the original memory layout to match.
-    let mut window: Vec<u8> = vec![0; 2 * WINDOW_SIZE + MAX_MATCH];
-
-    const ORIGINAL_GZIP_GARBAGE: &[u8] = &[
-        0x00, 0x00, 0x00, 0x00, // inptr (0)
-        0x03, 0x00, 0x00, 0x00, // ifd (3)
-        0xB5, 0x2F, 0x05, 0x08, // z_suffix (0x08052FB5)
-        0x00, 0x00, 0x00, 0x00, // bk (0)
-        0x00, 0x00, 0x00, 0x00, // bb (0)
-        0x52, 0xD0, 0xFF, 0xFF, // file_type (0xFFFFD052)
-        0xD0, 0x4A, 0x05, 0x08, // file_method (0x08054AD0)
-        0x00, 0x00, 0x00, 0x00, // decrypt (0)
-        0x00, 0x00, 0x00, 0x00, // key (0)
-        0x0A, 0x00, 0x00, 0x00, // header_bytes (10)
-              // Remaining bytes are 0
-    ];
-    window[2 * WINDOW_SIZE..2 * WINDOW_SIZE + ORIGINAL_GZIP_GARBAGE.len()]
-        .copy_from_slice(ORIGINAL_GZIP_GARBAGE);
+// Old gzip versions can read past the window into memory used for other global
+// variables, which can affect compression output. We allocate a little extra
+// space and reproduce the original memory layout to match.
+const ORIGINAL_GZIP_GARBAGE: &[u8] = &[
+    0x00, 0x00, 0x00, 0x00, // inptr (0)
+    0x03, 0x00, 0x00, 0x00, // ifd (3)
+    0xB5, 0x2F, 0x05, 0x08, // z_suffix (0x08052FB5)
+    0x00, 0x00, 0x00, 0x00, // bk (0)
+    0x00, 0x00, 0x00, 0x00, // bb (0)
+    0x52, 0xD0, 0xFF, 0xFF, // file_type (0xFFFFD052)
+    0xD0, 0x4A, 0x05, 0x08, // file_method (0x08054AD0)
+    0x00, 0x00, 0x00, 0x00, // decrypt (0)
+    0x00, 0x00, 0x00, 0x00, // key (0)
+    0x0A, 0x00, 0x00, 0x00, // header_bytes (10)
+          // Remaining bytes are 0
+];
 
+// Offset of `header_bytes` in `ORIGINAL_GZIP_GARBAGE`
+const HEADER_BYTES_OFFSET: usize = 36;
+
+// Compressor state, mirroring the global variables of the original gzip code
+struct Deflater<'a> {
+    // Compression input
+    input: &'a [u8],
+    config: &'static Config,
+    hash_mask: usize,
+    output: OutputStream,
+    writer: BlockWriter,
+    // Sliding window, with extra space at the end (see `Deflater::new`)
+    window: Vec<u8>,
     // Position in input buffer
-    let mut input_pos = cmp::min(2 * WINDOW_SIZE, input_size);
+    input_pos: usize,
     // True if we have reached the end of input
-    let mut eof: bool = input_size < 2 * WINDOW_SIZE;
+    eof: bool,
     // Length of current block
-    let mut block_length: usize = 0;
-
-    // Copy start of input into window
-    window[0..input_pos].copy_from_slice(&bytes[0..input_pos]);
-    hasher.update(&bytes[0..input_pos]);
-
+    block_length: usize,
     // Current position in window
-    let mut pos: usize = 0;
+    pos: usize,
     // Number of bytes left in window
-    let mut lookahead = input_pos;
-
+    lookahead: usize,
     // Heads of hash chains
-    let mut head: Vec<usize> = vec![NIL; hash_size];
+    head: Vec<usize>,
     // Next pointers in hash chains
-    let mut next: Vec<usize> = vec![NIL; WINDOW_SIZE];
-
+    next: Vec<usize>,
     // Current hash value
-    let mut hash: usize = 0;
-    hash = update_hash(hash, window[0], hash_mask);
-    hash = update_hash(hash, window[1], hash_mask);
-
-    // True if we haven't emitted the previous character yet (either as a
-    // literal or a match)
-    let mut has_prev_char: bool = false;
-    // Best match length for previous character
-    let mut prev_match_len: usize = MIN_MATCH - 1;
-    // Best match distance for previous character
-    let mut prev_match_dist: usize = 0;
-
-    while lookahead > 0 {
-        // Insert new string into the hash table
-        hash = update_hash(hash, window[pos + MIN_MATCH - 1], hash_mask);
-        next[pos & WINDOW_MASK] = head[hash];
-        head[hash] = pos;
-
-        // Find the longest match
-        let mut match_pos = next[pos & WINDOW_MASK];
+    hash: usize,
+}
+
+impl Deflater<'_> {
+    fn new<'a>(
+        input: &'a [u8],
+        level: usize,
+        small_mem: bool,
+        header: &[u8],
+    ) -> Result<Deflater<'a>, Crunch64Error> {
+        let input_size = input.len();
+
+        let buffer_size = if small_mem { 0x2000 } else { 0x8000 };
+        let hash_bits = if small_mem { 13 } else { 15 };
+        let hash_size = 1 << hash_bits;
+        let hash_mask = hash_size - 1;
+
+        let mut window: Vec<u8> = vec![0; 2 * WINDOW_SIZE + MAX_MATCH];
+        window[2 * WINDOW_SIZE..2 * WINDOW_SIZE + ORIGINAL_GZIP_GARBAGE.len()]
+            .copy_from_slice(ORIGINAL_GZIP_GARBAGE);
+        // The original gzip header was 10 bytes long unless it included a file name
+        let header_bytes = cmp::max(header.len(), 10) as u32;
+        window[2 * WINDOW_SIZE + HEADER_BYTES_OFFSET..2 * WINDOW_SIZE + HEADER_BYTES_OFFSET + 4]
+            .copy_from_slice(&header_bytes.to_le_bytes());
+
+        // Copy start of input into window
+        let input_pos = cmp::min(2 * WINDOW_SIZE, input_size);
+        window[0..input_pos].copy_from_slice(&input[0..input_pos]);
+
+        let mut hash: usize = 0;
+        hash = update_hash(hash, window[0], hash_mask);
+        hash = update_hash(hash, window[1], hash_mask);
+
+        let mut output = OutputStream::new(header.len() + size_for_compressed_buffer(input_size)?);
+        output.write_bytes(header);
+
+        Ok(Deflater {
+            input,
+            config: &COMPRESSION_LEVELS[level],
+            hash_mask,
+            output,
+            writer: BlockWriter::new(buffer_size, level),
+            window,
+            input_pos,
+            eof: input_size < 2 * WINDOW_SIZE,
+            block_length: 0,
+            pos: 0,
+            lookahead: input_pos,
+            head: vec![NIL; hash_size],
+            next: vec![NIL; WINDOW_SIZE],
+            hash,
+        })
+    }
+
+    // Inserts the string at `pos` into the hash table, returning the previous
+    // head of its hash chain
+    fn insert_string(&mut self, pos: usize) -> usize {
+        self.hash = update_hash(self.hash, self.window[pos + MIN_MATCH - 1], self.hash_mask);
+        let head = self.head[self.hash];
+        self.next[pos & WINDOW_MASK] = head;
+        self.head[self.hash] = pos;
+        head
+    }
+
+    // Searches the hash chain starting at `match_pos` for the longest match at
+    // the current position, returning its position and length. Only matches
+    // longer than `prev_len` are considered.
+    fn longest_match(&self, mut match_pos: usize, prev_len: usize) -> (usize, usize) {
+        let window = &self.window;
+        let pos = self.pos;
+
+        // Bound for number of potential matches to check. If the previous
+        // match is "good" we don't check as many.
+        let mut chain_length = if prev_len >= self.config.good_match {
+            self.config.max_chain_length / 4
+        } else {
+            self.config.max_chain_length
+        };
+
+        // Earliest position to check for matches
+        let limit = if pos > MAX_DIST { pos - MAX_DIST } else { NIL };
+
         let mut best_pos = 0;
-        let mut best_len = prev_match_len;
-        if match_pos != NIL && prev_match_len < config.max_lazy_match && pos - match_pos <= MAX_DIST
-        {
-            // Bound for number of potential matches to check. If the previous
-            // match is "good" we don't check as many.
-            let mut chain_length = if prev_match_len >= config.good_match {
-                config.max_chain_length / 4
+        let mut best_len = prev_len;
+        loop {
+            if window[match_pos] == window[pos]
+                && window[match_pos + 1] == window[pos + 1]
+                && window[match_pos + best_len] == window[pos + best_len]
+            {
+                // The hash function guarantees that if the hashes are equal and
+                // the first two bytes match, the third byte will too
+                let candidate_length = 3 + utils::longest_common_prefix(
+                    &window[match_pos + 3..match_pos + MAX_MATCH],
+                    &window[pos + 3..pos + MAX_MATCH],
+                );
+                if candidate_length > best_len {
+                    best_pos = match_pos;
+                    best_len = candidate_length;
+                    if best_len >= self.config.nice_match {
+                        break;
+                    }
+                }
+            }
+
+            match_pos = self.next[match_pos & WINDOW_MASK];
+            chain_length -= 1;
+            if match_pos <= limit || chain_length == 0 {
+                break;
+            }
+        }
+
+        (best_pos, best_len)
+    }
+
+    // Ends the current block, which extends up to the current position
+    fn flush_block(&mut self, eof: bool) {
+        if self.pos >= self.block_length {
+            let block = &self.window[self.pos - self.block_length..self.pos];
+            self.writer.flush_block(&mut self.output, Some(block), eof);
+        } else {
+            self.writer.flush_block(&mut self.output, None, eof);
+        }
+        self.block_length = 0;
+    }
+
+    // Slides the window and reads more input once we're close to the end of it
+    fn fill_window(&mut self) {
+        if self.lookahead >= MIN_LOOKAHEAD || self.eof || self.pos < WINDOW_SIZE + MAX_DIST {
+            return;
+        }
+
+        self.window.copy_within(WINDOW_SIZE..2 * WINDOW_SIZE, 0);
+
+        self.pos -= WINDOW_SIZE;
+        for i in &mut self.head {
+            *i = if *i >= WINDOW_SIZE {
+                *i - WINDOW_SIZE
+            } else {
+                NIL
+            };
+        }
+        for i in &mut self.next {
+            *i = if *i >= WINDOW_SIZE {
+                *i - WINDOW_SIZE
             } else {
-                config.max_chain_length
+                NIL
             };
+        }
 
-            // Earliest position to check for matches
-            let limit = if pos > MAX_DIST { pos - MAX_DIST } else { NIL };
-
-            loop {
-                if window[match_pos] == window[pos]
-                    && window[match_pos + 1] == window[pos + 1]
-                    && window[match_pos + best_len] == window[pos + best_len]
-                {
-                    // The hash function guarantees that if the hashes are equal and
-                    // the first two bytes match, the third byte will too
-                    let candidate_length = 3 + utils::longest_common_prefix(
-                        &window[match_pos + 3..match_pos + MAX_MATCH],
-                        &window[pos + 3..pos + MAX_MATCH],
-                    );
-                    if candidate_length > best_len {
-                        best_pos = match_pos;
-                        best_len = candidate_length;
-                        if best_len >= config.nice_match {
-                            break;
-                        }
-                    }
-                }
+        let refill_start = self.input_pos;
+        let refill_end = cmp::min(refill_start + WINDOW_SIZE, self.input.len());
+        let refill_size = refill_end - refill_start;
+        self.window[WINDOW_SIZE..WINDOW_SIZE + refill_size]
+            .copy_from_slice(&self.input[refill_start..refill_end]);
 
-                match_pos = next[match_pos & WINDOW_MASK];
-                chain_length -= 1;
-                if match_pos <= limit || chain_length == 0 {
-                    break;
+        self.input_pos = refill_end;
+        self.lookahead += refill_size;
+        if refill_size == 0 {
+            self.eof = true;
+        }
+    }
+
+    // Compression with lazy matching, used for levels 4-9: a match is only
+    // emitted if there is no longer match starting at the next byte.
+    fn deflate_slow(&mut self) {
+        // True if we haven't emitted the previous character yet (either as a
+        // literal or a match)
+        let mut has_prev_char: bool = false;
+        // Best match length for previous character
+        let mut prev_match_len: usize = MIN_MATCH - 1;
+        // Best match distance for previous character
+        let mut prev_match_dist: usize = 0;
+
+        while self.lookahead > 0 {
+            // Insert new string into the hash table
+            let match_pos = self.insert_string(self.pos);
+
+            // Find the longest match
+            let mut best_pos = 0;
+            let mut best_len = prev_match_len;
+            if match_pos != NIL
+                && prev_match_len < self.config.max_lazy_match
+                && self.pos - match_pos <= MAX_DIST
+            {
+                (best_pos, best_len) = self.longest_match(match_pos, prev_match_len);
+
+                best_len = cmp::min(best_len, self.lookahead);
+                if best_len == MIN_MATCH && self.pos - best_pos > TOO_FAR {
+                    best_len = MIN_MATCH - 1;
                 }
             }
 
-            best_len = cmp::min(best_len, lookahead);
-            if best_len == MIN_MATCH && pos - best_pos > TOO_FAR {
-                best_len = MIN_MATCH - 1;
+            let mut should_flush = false;
+            if prev_match_len >= MIN_MATCH && prev_match_len >= best_len {
+                // Emit previous match
+                self.writer.add_match(prev_match_len, prev_match_dist);
+                should_flush = self.writer.should_flush_block(self.block_length);
+
+                // Insert new strings in the hash table
+                for i in 1..prev_match_len - 1 {
+                    self.insert_string(self.pos + i);
+                }
+
+                self.block_length += prev_match_len - 1;
+                self.pos += prev_match_len - 1;
+                self.lookahead -= prev_match_len - 1;
+
+                if should_flush {
+                    self.flush_block(false);
+                }
+
+                has_prev_char = false;
+                prev_match_len = MIN_MATCH - 1;
+                prev_match_dist = 0;
+            } else {
+                // Emit previous character as literal (if it exists) and remember current match
+                if has_prev_char {
+                    self.writer.add_literal(self.window[self.pos - 1]);
+                    should_flush = self.writer.should_flush_block(self.block_length);
+                }
+
+                if should_flush {
+                    self.flush_block(false);
+                }
+
+                self.block_length += 1;
+                self.pos += 1;
+                self.lookahead -= 1;
+
+                has_prev_char = true;
+                prev_match_len = best_len;
+                prev_match_dist = self.pos - 1 - best_pos;
             }
+
+            self.fill_window();
+        }
+
+        if has_prev_char {
+            self.writer.add_literal(self.window[self.pos - 1]);
         }
 
-        let mut should_flush = false;
-        if prev_match_len >= MIN_MATCH && prev_match_len >= best_len {
-            // Emit previous match
-            writer.add_match(prev_match_len, prev_match_dist);
-            should_flush = writer.should_flush_block(block_length);
+        self.flush_block(true);
+    }
 
-            // Insert new strings in the hash table
-            for i in 1..prev_match_len - 1 {
-                hash = update_hash(hash, window[pos + i + MIN_MATCH - 1], hash_mask);
-                next[(pos + i) & WINDOW_MASK] = head[hash];
-                head[hash] = pos + i;
+    // Compression without lazy matching, used for levels 1-3: matches are
+    // emitted as soon as they are found, and only short matches are inserted
+    // into the hash table.
+    fn deflate_fast(&mut self) {
+        while self.lookahead > 0 {
+            // Insert new string into the hash table
+            let match_pos = self.insert_string(self.pos);
+
+            // Find the longest match
+            let mut match_len = 0;
+            let mut match_dist = 0;
+            if match_pos != NIL && self.pos - match_pos <= MAX_DIST {
+                let (best_pos, best_len) = self.longest_match(match_pos, MIN_MATCH - 1);
+                match_len = cmp::min(best_len, self.lookahead);
+                match_dist = self.pos - best_pos;
             }
 
-            block_length += prev_match_len - 1;
-            pos += prev_match_len - 1;
-            lookahead -= prev_match_len - 1;
+            let should_flush;
+            if match_len >= MIN_MATCH {
+                self.writer.add_match(match_len, match_dist);
+                should_flush = self.writer.should_flush_block(self.block_length);
 
-            if should_flush {
-                if pos >= block_length {
-                    writer.flush_block(&mut output, Some(&window[pos - block_length..pos]), false);
+                if match_len <= self.config.max_lazy_match {
+                    // Insert new strings in the hash table
+                    for i in 1..match_len {
+                        self.insert_string(self.pos + i);
+                    }
                 } else {
-                    writer.flush_block(&mut output, None, false);
+                    // Skip the match entirely and restart hashing after it
+                    let pos = self.pos + match_len;
+                    self.hash = 0;
+                    self.hash = update_hash(self.hash, self.window[pos], self.hash_mask);
+                    self.hash = update_hash(self.hash, self.window[pos + 1], self.hash_mask);
                 }
-                block_length = 0;
-            }
 
-            has_prev_char = false;
-            prev_match_len = MIN_MATCH - 1;
-            prev_match_dist = 0;
-        } else {
-            // Emit previous character as literal (if it exists) and remember current match
-            if has_prev_char {
-                writer.add_literal(window[pos - 1]);
-                should_flush = writer.should_flush_block(block_length);
+                self.block_length += match_len;
+                self.pos += match_len;
+                self.lookahead -= match_len;
+            } else {
+                // Emit current character as literal
+                self.writer.add_literal(self.window[self.pos]);
+                should_flush = self.writer.should_flush_block(self.block_length);
+
+                self.block_length += 1;
+                self.pos += 1;
+                self.lookahead -= 1;
             }
 
             if should_flush {
-                if pos >= block_length {
-                    writer.flush_block(&mut output, Some(&window[pos - block_length..pos]), false);
-                } else {
-                    writer.flush_block(&mut output, None, false);
-                }
-                block_length = 0;
+                self.flush_block(false);
             }
 
-            block_length += 1;
-            pos += 1;
-            lookahead -= 1;
+            self.fill_window();
+        }
+
+        self.flush_block(true);
+    }
+}
+
+// Compresses `bytes` into a DEFLATE stream written after `header`, padded to a
+// byte boundary so that a footer can be appended.
+pub(crate) fn deflate(
+    bytes: &[u8],
+    level: usize,
+    small_mem: bool,
+    header: &[u8],
+) -> Result<Vec<u8>, Crunch64Error> {
+    // Level 0 (no compression) is not supported by gzip
+    if !(1..=9).contains(&level) {
+        return Err(Crunch64Error::InvalidCompressionLevel);
+    }
+
+    let mut deflater = Deflater::new(bytes, level, small_mem, header)?;
+    if level <= 3 {
+        deflater.deflate_fast();
+    } else {
+        deflater.deflate_slow();
+    }
+
+    Ok(deflater.output.into_vec())
+}
+
+/// Options for [`compress_with_options`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressOptions {
+    /// Compression level (1-9)
+    pub level: usize,
+    /// Output blocks more frequently, like gzip built with `SMALL_MEM`
+    pub small_mem: bool,
+    /// gzip header to write before the compressed data. If `None` then the
+    /// header is omitted and only the footer is written.
+    pub header: Option<GzipHeader>,
+}
+
+impl CompressOptions {
+    pub const DEFAULT: CompressOptions = CompressOptions {
+        level: 9,
+        small_mem: false,
+        header: None,
+    };
+}
+
+impl Default for CompressOptions {
+    fn default() -> CompressOptions {
+        CompressOptions::DEFAULT
+    }
+}
+
+pub fn compress(bytes: &[u8], level: usize, small_mem: bool) -> Result<Box<[u8]>, Crunch64Error> {
+    compress_with_options(
+        bytes,
+        &CompressOptions {
+            level,
+            small_mem,
+            header: None,
+        },
+    )
+}
 
-            has_prev_char = true;
-            prev_match_len = best_len;
-            prev_match_dist = pos - 1 - best_pos;
+pub fn compress_with_options(
+    bytes: &[u8],
+    options: &CompressOptions,
+) -> Result<Box<[u8]>, Crunch64Error> {
+    let level = options.level;
+
+    let mut header: Vec<u8> = Vec::new();
+    if let Some(gzip_header) = &options.header {
+        // gzip sets the extra flags according to the compression level
+        let xfl = match level {
+            1 => XFL_FAST,
+            9 => XFL_SLOW,
+            _ => 0,
+        };
+        GzipHeader {
+            xfl,
+            ..gzip_header.clone()
         }
+        .write(&mut header);
+    }
 
-        // Refill window
-        if lookahead < MIN_LOOKAHEAD && !eof && pos >= WINDOW_SIZE + MAX_DIST {
-            window.copy_within(WINDOW_SIZE..2 * WINDOW_SIZE, 0);
+    let mut output = deflate(bytes, level, options.small_mem, &header)?;
+    output.extend(crc32fast::hash(bytes).to_le_bytes());
+    output.extend((bytes.len() as u32).to_le_bytes());
 
-            pos -= WINDOW_SIZE;
-            for i in &mut head {
-                *i = if *i >= WINDOW_SIZE {
-                    *i - WINDOW_SIZE
-                } else {
-                    NIL
-                };
+    Ok(output.into_boxed_slice())
+}
+
+/// Compresses `bytes` into a raw DEFLATE stream, without any gzip header or
+/// footer. The stream is identical to the one produced by [`compress`].
+pub fn compress_raw(
+    bytes: &[u8],
+    level: usize,
+    small_mem: bool,
+) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(deflate(bytes, level, small_mem, &[])?.into_boxed_slice())
+}
+
+// Bitstream reader for compressed input
+struct InputStream<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    bit_buffer: u32,
+    bit_count: u8,
+}
+
+impl InputStream<'_> {
+    fn new(bytes: &[u8]) -> InputStream<'_> {
+        InputStream {
+            bytes,
+            pos: 0,
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn read_bits(&mut self, length: u8) -> Result<u16, Crunch64Error> {
+        while self.bit_count < length {
+            let byte = *self
+                .bytes
+                .get(self.pos)
+                .ok_or(Crunch64Error::TruncatedInput)?;
+            self.bit_buffer |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+            self.pos += 1;
+        }
+
+        let value = self.bit_buffer & ((1 << length) - 1);
+        self.bit_buffer >>= length;
+        self.bit_count -= length;
+        Ok(value as u16)
+    }
+
+    // Discards the remaining bits of the current byte, returning them. Bytes
+    // are only loaded into the bit buffer when needed, so this never skips a
+    // whole byte.
+    fn align_to_byte(&mut self) -> u32 {
+        let bits = self.bit_buffer;
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+        bits
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&[u8], Crunch64Error> {
+        self.align_to_byte();
+        if self.pos + length > self.bytes.len() {
+            return Err(Crunch64Error::TruncatedInput);
+        }
+
+        let bytes = &self.bytes[self.pos..self.pos + length];
+        self.pos += length;
+        Ok(bytes)
+    }
+}
+
+// Canonical Huffman decoder. Instead of building a lookup table, codes are
+// decoded one bit at a time by counting the number of codes of each length
+// (similar to zlib's `puff.c`).
+struct HuffmanDecoder {
+    // Number of symbols for each code length
+    length_counts: [u16; 16],
+    // Symbols ordered by code length, then by symbol value
+    symbols: Vec<u16>,
+}
+
+impl HuffmanDecoder {
+    fn new(lengths: &[u8]) -> Result<HuffmanDecoder, Crunch64Error> {
+        let mut length_counts = [0u16; 16];
+        for &length in lengths {
+            length_counts[length as usize] += 1;
+        }
+        length_counts[0] = 0;
+
+        // Reject over-subscribed codes. Incomplete codes are allowed, decoding
+        // an unused code word is reported as an error instead.
+        let mut left: i32 = 1;
+        for &count in &length_counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(Crunch64Error::InvalidDeflateStream);
             }
-            for i in &mut next {
-                *i = if *i >= WINDOW_SIZE {
-                    *i - WINDOW_SIZE
-                } else {
-                    NIL
-                };
+        }
+
+        let mut offsets = [0u16; 16];
+        for i in 1..15 {
+            offsets[i + 1] = offsets[i] + length_counts[i];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
             }
+        }
+
+        Ok(HuffmanDecoder {
+            length_counts,
+            symbols,
+        })
+    }
+
+    fn decode(&self, input: &mut InputStream) -> Result<usize, Crunch64Error> {
+        // First code word of the current length, and its index in `symbols`
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+
+        for &count in &self.length_counts[1..] {
+            code |= input.read_bits(1)? as i32;
+            if code - first < count as i32 {
+                return Ok(self.symbols[(index + code - first) as usize] as usize);
+            }
+            index += count as i32;
+            first = (first + count as i32) << 1;
+            code <<= 1;
+        }
+
+        Err(Crunch64Error::InvalidDeflateStream)
+    }
+}
+
+fn inflate_block<O: Output>(
+    input: &mut InputStream,
+    output: &mut O,
+    lcode: &HuffmanDecoder,
+    dcode: &HuffmanDecoder,
+) -> Result<(), Crunch64Error> {
+    loop {
+        let lsymbol = lcode.decode(input)?;
+        if lsymbol < END {
+            output.push(lsymbol as u8)?;
+        } else if lsymbol == END {
+            return Ok(());
+        } else {
+            if lsymbol >= L_NUM_SYMBOLS {
+                return Err(Crunch64Error::InvalidDeflateStream);
+            }
+            let length = MIN_MATCH
+                + BASE_LENGTH[lsymbol] as usize
+                + input.read_bits(L_EXTRA_BITS[lsymbol])? as usize;
+
+            let dsymbol = dcode.decode(input)?;
+            if dsymbol >= D_NUM_SYMBOLS {
+                return Err(Crunch64Error::InvalidDeflateStream);
+            }
+            let distance = 1
+                + BASE_DISTANCE[dsymbol] as usize
+                + input.read_bits(D_EXTRA_BITS[dsymbol])? as usize;
+
+            // DEFLATE doesn't store the uncompressed size, so only the size of
+            // `output` limits the copy
+            utils::copy_back_reference(output, distance, length, usize::MAX)?;
+        }
+    }
+}
 
-            let refill_start = input_pos;
-            let refill_end = cmp::min(refill_start + WINDOW_SIZE, input_size);
-            let refill_size = refill_end - refill_start;
-            window[WINDOW_SIZE..WINDOW_SIZE + refill_size]
-                .copy_from_slice(&bytes[refill_start..refill_end]);
-            hasher.update(&bytes[refill_start..refill_end]);
-
-            input_pos = refill_end;
-            lookahead += refill_size;
-            if refill_size == 0 {
-                eof = true;
+fn read_dynamic_codes(
+    input: &mut InputStream,
+) -> Result<(HuffmanDecoder, HuffmanDecoder), Crunch64Error> {
+    let num_lsymbols = input.read_bits(5)? as usize + 257;
+    let num_dsymbols = input.read_bits(5)? as usize + 1;
+    let num_bl_indices = input.read_bits(4)? as usize + 4;
+
+    if num_lsymbols > L_NUM_SYMBOLS || num_dsymbols > D_NUM_SYMBOLS {
+        return Err(Crunch64Error::InvalidDeflateStream);
+    }
+
+    let mut blengths = [0u8; B_NUM_SYMBOLS];
+    for &bsymbol in B_SYMBOL_ORDER.iter().take(num_bl_indices) {
+        blengths[bsymbol as usize] = input.read_bits(3)? as u8;
+    }
+    let bcode = HuffmanDecoder::new(&blengths)?;
+
+    // Literal/length and distance code lengths are sent as a single sequence
+    let mut lengths: Vec<u8> = Vec::with_capacity(num_lsymbols + num_dsymbols);
+    while lengths.len() < num_lsymbols + num_dsymbols {
+        let bsymbol = bcode.decode(input)?;
+        let (length, repeat) = match bsymbol {
+            0..=15 => (bsymbol as u8, 1),
+            16 => match lengths.last() {
+                Some(&prev) => (prev, 3 + input.read_bits(2)? as usize),
+                None => return Err(Crunch64Error::InvalidDeflateStream),
+            },
+            17 => (0, 3 + input.read_bits(3)? as usize),
+            _ => (0, 11 + input.read_bits(7)? as usize),
+        };
+
+        if lengths.len() + repeat > num_lsymbols + num_dsymbols {
+            return Err(Crunch64Error::InvalidDeflateStream);
+        }
+        lengths.resize(lengths.len() + repeat, length);
+    }
+
+    // The end-of-block symbol must be encodable
+    if lengths[END] == 0 {
+        return Err(Crunch64Error::InvalidDeflateStream);
+    }
+
+    let lcode = HuffmanDecoder::new(&lengths[..num_lsymbols])?;
+    let dcode = HuffmanDecoder::new(&lengths[num_lsymbols..])?;
+    Ok((lcode, dcode))
+}
+
+// Decompresses a DEFLATE stream, leaving `input` at the byte following the
+// final block. Returns the unused bits of the last byte, which encoders set
+// to zero.
+fn inflate_stream<O: Output>(
+    input: &mut InputStream,
+    output: &mut O,
+) -> Result<u32, Crunch64Error> {
+    loop {
+        let eof = input.read_bits(1)? != 0;
+        match input.read_bits(2)? {
+            0b00 => {
+                let header = input.read_bytes(4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len != !nlen {
+                    return Err(Crunch64Error::InvalidDeflateStream);
+                }
+                output.extend_from_slice(input.read_bytes(len as usize)?)?;
+            }
+            0b01 => {
+                let lcode = HuffmanDecoder::new(&FIXED_LCODE_LENGTHS)?;
+                let dcode = HuffmanDecoder::new(&FIXED_DCODE_LENGTHS)?;
+                inflate_block(input, output, &lcode, &dcode)?;
+            }
+            0b10 => {
+                let (lcode, dcode) = read_dynamic_codes(input)?;
+                inflate_block(input, output, &lcode, &dcode)?;
             }
+            _ => return Err(Crunch64Error::InvalidDeflateStream),
+        }
+
+        if eof {
+            return Ok(input.align_to_byte());
         }
     }
+}
+
+// Decompresses the DEFLATE stream at the start of `bytes`, returning the
+// decompressed data and the size of the stream in bytes
+pub(crate) fn inflate(bytes: &[u8]) -> Result<(Vec<u8>, usize), Crunch64Error> {
+    let mut output: Vec<u8> = Vec::new();
+    let stream_size = inflate_into(bytes, &mut output)?;
+
+    Ok((output, stream_size))
+}
+
+// Decompresses the DEFLATE stream at the start of `bytes` into `output`,
+// returning the size of the stream in bytes
+pub(crate) fn inflate_into<O: Output>(
+    bytes: &[u8],
+    output: &mut O,
+) -> Result<usize, Crunch64Error> {
+    let mut input = InputStream::new(bytes);
+    inflate_stream(&mut input, output)?;
+
+    Ok(input.pos)
+}
+
+// Like `inflate`, but fails if the unused bits after the final block aren't
+// zero. Encoders always write zeros there, while random data that happens to
+// decode as a DEFLATE stream usually doesn't.
+pub(crate) fn inflate_zero_padded(bytes: &[u8]) -> Result<(Vec<u8>, usize), Crunch64Error> {
+    let mut input = InputStream::new(bytes);
+    let mut output: Vec<u8> = Vec::new();
+    if inflate_stream(&mut input, &mut output)? != 0 {
+        return Err(Crunch64Error::InvalidDeflateStream);
+    }
+
+    Ok((output, input.pos))
+}
+
+// gzip header flags
+const FTEXT: u8 = 1 << 0;
+const FHCRC: u8 = 1 << 1;
+const FEXTRA: u8 = 1 << 2;
+const FNAME: u8 = 1 << 3;
+const FCOMMENT: u8 = 1 << 4;
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+// Extra flags
+const XFL_SLOW: u8 = 2;
+const XFL_FAST: u8 = 4;
+
+// Operating system written by gzip on Unix
+const OS_UNIX: u8 = 3;
+
+/// Fields of an RFC 1952 gzip header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GzipHeader {
+    /// Original file name (FNAME), without the terminating zero byte
+    pub filename: Option<Vec<u8>>,
+    /// File comment (FCOMMENT), without the terminating zero byte
+    pub comment: Option<Vec<u8>>,
+    /// Modification time of the original file, as a Unix timestamp
+    pub mtime: u32,
+    /// Extra flags. [`compress_with_options`] ignores this field and sets it
+    /// from the compression level like gzip does (2 for level 9, 4 for level 1).
+    pub xfl: u8,
+    /// Operating system the file was compressed on
+    pub os: u8,
+}
+
+impl Default for GzipHeader {
+    fn default() -> GzipHeader {
+        GzipHeader {
+            filename: None,
+            comment: None,
+            mtime: 0,
+            xfl: 0,
+            os: OS_UNIX,
+        }
+    }
+}
+
+impl GzipHeader {
+    /// Parses the gzip header at the start of `bytes`, returning the header
+    /// and its size in bytes
+    pub fn parse(bytes: &[u8]) -> Result<(GzipHeader, usize), Crunch64Error> {
+        if bytes.len() < 10 || bytes[0..2] != GZIP_MAGIC || bytes[2] != 8 {
+            return Err(Crunch64Error::InvalidGzipHeader);
+        }
+
+        let flags = bytes[3];
+        if flags & !(FTEXT | FHCRC | FEXTRA | FNAME | FCOMMENT) != 0 {
+            return Err(Crunch64Error::InvalidGzipHeader);
+        }
+
+        let mut header = GzipHeader {
+            mtime: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            xfl: bytes[8],
+            os: bytes[9],
+            ..Default::default()
+        };
+
+        let mut pos = 10;
+        if flags & FEXTRA != 0 {
+            let xlen = match bytes.get(pos..pos + 2) {
+                Some(xlen) => u16::from_le_bytes([xlen[0], xlen[1]]) as usize,
+                None => return Err(Crunch64Error::InvalidGzipHeader),
+            };
+            pos += 2 + xlen;
+        }
+        if flags & FNAME != 0 {
+            let (filename, len) = read_zero_terminated(bytes, pos)?;
+            header.filename = Some(filename);
+            pos += len;
+        }
+        if flags & FCOMMENT != 0 {
+            let (comment, len) = read_zero_terminated(bytes, pos)?;
+            header.comment = Some(comment);
+            pos += len;
+        }
+        if flags & FHCRC != 0 {
+            pos += 2;
+        }
+
+        if pos > bytes.len() {
+            return Err(Crunch64Error::InvalidGzipHeader);
+        }
+        Ok((header, pos))
+    }
+
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        let mut flags = 0;
+        if self.filename.is_some() {
+            flags |= FNAME;
+        }
+        if self.comment.is_some() {
+            flags |= FCOMMENT;
+        }
+
+        dst.extend(GZIP_MAGIC);
+        dst.push(8); // compression method (DEFLATE)
+        dst.push(flags);
+        dst.extend(self.mtime.to_le_bytes());
+        dst.push(self.xfl);
+        dst.push(self.os);
+        for field in [&self.filename, &self.comment].into_iter().flatten() {
+            dst.extend(field);
+            dst.push(0);
+        }
+    }
+}
+
+// Reads a zero-terminated string, returning it (without the terminator) and
+// the number of bytes read
+fn read_zero_terminated(bytes: &[u8], pos: usize) -> Result<(Vec<u8>, usize), Crunch64Error> {
+    match bytes
+        .get(pos..)
+        .and_then(|s| s.iter().position(|&b| b == 0))
+    {
+        Some(len) => Ok((bytes[pos..pos + len].to_vec(), len + 1)),
+        None => Err(Crunch64Error::InvalidGzipHeader),
+    }
+}
+
+fn read_footer(bytes: &[u8]) -> Result<(u32, u32), Crunch64Error> {
+    if bytes.len() < 8 {
+        return Err(Crunch64Error::TruncatedInput);
+    }
+
+    let crc = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+    let size = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    Ok((crc, size))
+}
+
+/// Decompresses a DEFLATE stream followed by a gzip footer, as produced by
+/// [`compress`]. Data starting with a full RFC 1952 gzip header is accepted too.
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_header(bytes)?.0)
+}
+
+/// Decompresses a raw DEFLATE stream, as produced by [`compress_raw`]. Any data
+/// after the end of the stream is ignored.
+pub fn decompress_raw(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(inflate(bytes)?.0.into_boxed_slice())
+}
+
+/// Like [`decompress_raw`], but also returns the size of the DEFLATE stream
+pub fn decompress_raw_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let (output, stream_size) = inflate(bytes)?;
+    Ok((output.into_boxed_slice(), stream_size))
+}
+
+/// Same as [`decompress_raw`], but decompresses into the start of `dst` and
+/// returns the decompressed size
+pub fn decompress_raw_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let mut output = SliceOutput::new(dst);
+    inflate_into(bytes, &mut output)?;
+    Ok(output.len())
+}
+
+// Checks whether `trailer` starts with the gzip footer of `output`
+pub(crate) fn footer_matches(trailer: &[u8], output: &[u8]) -> bool {
+    match read_footer(trailer) {
+        Ok((crc, size)) => crc == crc32fast::hash(output) && size == output.len() as u32,
+        Err(_) => false,
+    }
+}
+
+/// Guesses the format of data that starts with a DEFLATE stream instead of a
+/// header. The stream is gzip data if it's followed by a valid gzip footer, or
+/// a raw DEFLATE stream if it's only followed by zero padding.
+pub(crate) fn detect_headerless(bytes: &[u8]) -> Option<Format> {
+    let (output, stream_size) = inflate(bytes).ok()?;
+    let trailer = &bytes[stream_size..];
+
+    if trailer.len() == 8 && footer_matches(trailer, &output) {
+        return Some(Format::Gzip);
+    }
+
+    if trailer.iter().all(|&b| b == 0) {
+        return Some(Format::Deflate);
+    }
+
+    None
+}
+
+/// Like [`decompress`], but also returns the gzip header if there is one
+pub fn decompress_with_header(
+    bytes: &[u8],
+) -> Result<(Box<[u8]>, Option<GzipHeader>), Crunch64Error> {
+    let mut output: Vec<u8> = Vec::new();
+    let (header, _) = decompress_to(bytes, &mut output)?;
+
+    Ok((output.into_boxed_slice(), header))
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data,
+/// including the header and footer. It can be followed by unrelated data in
+/// `bytes`.
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let mut output: Vec<u8> = Vec::new();
+    let (_, compressed_size) = decompress_to(bytes, &mut output)?;
+
+    Ok((output.into_boxed_slice(), compressed_size))
+}
+
+/// Same as [`decompress`], but decompresses into the start of `dst` and
+/// returns the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let mut output = SliceOutput::new(dst);
+    decompress_to(bytes, &mut output)?;
+    Ok(output.len())
+}
+
+// Returns the gzip header if there is one, and the size of the compressed data
+fn decompress_to<O: Output>(
+    bytes: &[u8],
+    output: &mut O,
+) -> Result<(Option<GzipHeader>, usize), Crunch64Error> {
+    let (header, start) = if bytes.starts_with(&GZIP_MAGIC) {
+        let (header, header_size) = GzipHeader::parse(bytes)?;
+        (Some(header), header_size)
+    } else {
+        (None, 0)
+    };
+
+    let stream_size = inflate_into(&bytes[start..], output)?;
 
-    if has_prev_char {
-        writer.add_literal(window[pos - 1]);
+    let (crc, size) = read_footer(&bytes[start + stream_size..])?;
+    if crc != crc32fast::hash(output.written()) || size != output.len() as u32 {
+        return Err(Crunch64Error::ChecksumMismatch);
     }
 
-    if pos >= block_length {
-        writer.flush_block(&mut output, Some(&window[pos - block_length..pos]), true);
+    Ok((header, start + stream_size + 8))
+}
+
+// Reads the footer that follows the DEFLATE stream. The stream has to be
+// decompressed to find its end, since the data may be followed by padding.
+fn find_footer(bytes: &[u8]) -> Result<(u32, u32), Crunch64Error> {
+    let start = if bytes.starts_with(&GZIP_MAGIC) {
+        GzipHeader::parse(bytes)?.1
     } else {
-        writer.flush_block(&mut output, None, true);
+        0
+    };
+
+    let (_, stream_size) = inflate(&bytes[start..])?;
+    read_footer(&bytes[start + stream_size..])
+}
+
+/// gzip [`Codec`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Gzip {
+    pub options: CompressOptions,
+}
+
+impl Gzip {
+    pub const DEFAULT: Gzip = Gzip {
+        options: CompressOptions::DEFAULT,
+    };
+}
+
+impl Codec for Gzip {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress_with_options(bytes, &self.options)
     }
 
-    output.write_bytes(&hasher.finalize().to_le_bytes());
-    output.write_bytes(&(input_size as u32).to_le_bytes());
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
 
-    Ok(output.into_boxed_slice())
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        let header_size = match &self.options.header {
+            Some(header) => {
+                let mut dst = Vec::new();
+                header.write(&mut dst);
+                dst.len()
+            }
+            None => 0,
+        };
+        Ok(header_size + size_for_compressed_buffer(input_size)?)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        // The uncompressed size is stored in the last 4 bytes of the footer
+        Ok(find_footer(bytes)?.1 as usize)
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        if bytes.starts_with(&GZIP_MAGIC) {
+            Ok(GzipHeader::parse(bytes)?.1)
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+/// Raw DEFLATE [`Codec`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Deflate {
+    /// Compression level (1-9)
+    pub level: usize,
+    /// Output compressed blocks more often
+    pub small_mem: bool,
+}
+
+impl Deflate {
+    pub const DEFAULT: Deflate = Deflate {
+        level: 9,
+        small_mem: false,
+    };
+}
+
+impl Default for Deflate {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Codec for Deflate {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress_raw(bytes, self.level, self.small_mem)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress_raw(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_raw_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_raw_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        // Raw DEFLATE streams don't store the uncompressed size, so the only
+        // way to know it is to decompress the data
+        Ok(decompress_raw(bytes)?.len())
+    }
+
+    fn header_size(&self, _bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        // Raw DEFLATE streams don't have a header
+        Ok(0)
+    }
 }
 
 #[cfg(feature = "c_bindings")]
 mod c_bindings {
     use core::ffi::c_int;
 
+    #[no_mangle]
+    pub extern "C" fn crunch64_gzip_decompress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        // The uncompressed size is stored in the last 4 bytes of the footer
+        match super::find_footer(bytes) {
+            Err(e) => return e,
+            Ok((_, value)) => unsafe { *dst_size = value as usize },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_gzip_decompress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
     #[no_mangle]
     pub extern "C" fn crunch64_gzip_compress_bound(
         dst_size: *mut usize,
@@ -1148,12 +2058,96 @@ mod c_bindings {
             return super::Crunch64Error::NullPointer;
         }
 
-        let bytes = match super::utils::u8_vec_from_pointer_array(src_len, src) {
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
             Err(e) => return e,
             Ok(d) => d,
         };
 
-        let data = match super::compress(&bytes, level as usize, small_mem) {
+        let data = match super::compress(bytes, level as usize, small_mem) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_gzip_decompress_raw_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        // Raw DEFLATE streams don't store the uncompressed size, so the only
+        // way to know it is to decompress the data
+        match super::decompress_raw(bytes) {
+            Err(e) => return e,
+            Ok(data) => unsafe { *dst_size = data.len() },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_gzip_decompress_raw(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::decompress_raw_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_gzip_compress_raw(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+        level: c_int,
+        small_mem: bool,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let data = match super::compress_raw(bytes, level as usize, small_mem) {
             Err(e) => return e,
             Ok(d) => d,
         };
@@ -1179,14 +2173,49 @@ pub(crate) mod python_bindings {
      */
 
     #[pyfunction]
-    #[pyo3(signature = (bytes, level=9, small_mem=false))]
+    pub(crate) fn decompress_gzip(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::decompress(&bytes)?.into()))
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (bytes, level=9, small_mem=false, header=false, name=None, mtime=0))]
     pub(crate) fn compress_gzip(
         bytes: Cow<[u8]>,
         level: usize,
         small_mem: bool,
+        header: bool,
+        name: Option<String>,
+        mtime: u32,
     ) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        let header = header.then(|| super::GzipHeader {
+            filename: name.map(String::into_bytes),
+            mtime,
+            ..Default::default()
+        });
+        let options = super::CompressOptions {
+            level,
+            small_mem,
+            header,
+        };
         Ok(Cow::Owned(
-            super::compress(&bytes, level, small_mem)?.into(),
+            super::compress_with_options(&bytes, &options)?.into(),
+        ))
+    }
+
+    #[pyfunction]
+    pub(crate) fn decompress_gzip_raw(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::decompress_raw(&bytes)?.into()))
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (bytes, level=9, small_mem=false))]
+    pub(crate) fn compress_gzip_raw(
+        bytes: Cow<[u8]>,
+        level: usize,
+        small_mem: bool,
+    ) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(
+            super::compress_raw(&bytes, level, small_mem)?.into(),
         ))
     }
 }
@@ -1194,7 +2223,7 @@ pub(crate) mod python_bindings {
 #[cfg(test)]
 #[cfg(feature = "std")]
 mod tests {
-    use crate::Crunch64Error;
+    use crate::{Codec, Crunch64Error};
     use core::panic;
     use rstest::rstest;
     use std::{
@@ -1219,6 +2248,136 @@ mod tests {
         buffer
     }
 
+    #[rstest]
+    fn test_matching_decompression(
+        #[files("../test_data/*.gzip-*")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let decompressed = super::decompress(compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    // `small.txt.gz` was created with `gzip -9 small.txt`, with the file's
+    // modification time set to 1700000000
+    fn small_txt_header() -> super::GzipHeader {
+        super::GzipHeader {
+            filename: Some(b"small.txt".to_vec()),
+            mtime: 1700000000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_decompression_with_header() -> Result<(), Crunch64Error> {
+        let decompressed_file = &read_test_file(PathBuf::from("../test_data/small.txt"));
+        let compressed_file = &read_test_file(PathBuf::from("../test_data/small.txt.gz"));
+
+        let (decompressed, header) = super::decompress_with_header(compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        assert_eq!(
+            header,
+            Some(super::GzipHeader {
+                xfl: 2,
+                ..small_txt_header()
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_matching_compression_with_header() -> Result<(), Crunch64Error> {
+        let decompressed_file = &read_test_file(PathBuf::from("../test_data/small.txt"));
+        let compressed_file = &read_test_file(PathBuf::from("../test_data/small.txt.gz"));
+
+        let options = super::CompressOptions {
+            header: Some(small_txt_header()),
+            ..Default::default()
+        };
+        let compressed = super::compress_with_options(decompressed_file, &options)?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_bad_checksum() {
+        let mut compressed_file = read_test_file(PathBuf::from("../test_data/small.txt.gzip-9"));
+        let len = compressed_file.len();
+        compressed_file[len - 8] ^= 0xFF;
+
+        assert_eq!(
+            super::decompress(&compressed_file),
+            Err(Crunch64Error::ChecksumMismatch)
+        );
+    }
+
+    #[test]
+    fn test_decompress_bound_padded() -> Result<(), Crunch64Error> {
+        let data: Vec<u8> = (0..5000u32).map(|i| ((i * i) >> 5) as u8).collect();
+        let mut compressed = super::compress(&data, 9, false)?.into_vec();
+        compressed.extend([0; 8]);
+
+        assert_eq!(super::decompress(&compressed)?.len(), 5000);
+        assert_eq!(super::Gzip::DEFAULT.decompress_bound(&compressed)?, 5000);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_matching_raw(
+        #[files("../test_data/*.gzip-9")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        // The raw stream is the gzip data without its 8-byte footer
+        let stream = &compressed_file[..compressed_file.len() - 8];
+
+        let decompressed = super::decompress_raw(stream)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+
+        let compressed = super::compress_raw(decompressed_file.as_slice(), 9, false)?;
+        assert_eq!(stream, compressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_matching_compression_level_1(
+        #[files("../test_data/*.gzip-1")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let compressed = super::compress(decompressed_file.as_slice(), 1, false)?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_matching_compression_level_2(
+        #[files("../test_data/*.gzip-2")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let compressed = super::compress(decompressed_file.as_slice(), 2, false)?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_matching_compression_level_3(
+        #[files("../test_data/*.gzip-3")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let compressed = super::compress(decompressed_file.as_slice(), 3, false)?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
     #[rstest]
     fn test_matching_compression_level_9(
         #[files("../test_data/*.gzip-9")] path: PathBuf,
@@ -0,0 +1,574 @@
+// Implements the LZ compression of HAL Laboratory's SNES games, as handled by
+// exhal. Their N64 games (e.g. Kirby 64 and Pokémon Stadium) are assumed to
+// use the same format, but no data from them has been decoded with this
+// module yet. There's no header, the data is a sequence of commands ended by
+// a 0xFF byte.
+//
+// The top 3 bits of a command byte select the command and the low 5 bits are
+// the length minus 1. If the top 3 bits are all set, the command is in the
+// next 3 bits instead and the length minus 1 takes 10 bits, using the
+// following byte too. The commands are:
+//
+// - 0: the next `length` bytes are copied as is.
+// - 1: the next byte is repeated `length` times.
+// - 2: the next 2 bytes are repeated `length` times.
+// - 3: the next byte is written `length` times, adding 1 after each time.
+// - 4: `length` bytes are copied from the big-endian 16-bit position that
+//   follows.
+// - 5: same as 4, but with the bits of each byte reversed.
+// - 6: same as 4, but going backwards from the position.
+//
+// Back-references use absolute positions, so they can only copy from the
+// first 64 KiB of the decompressed data.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    utils::{self, Output},
+    Codec, Crunch64Error,
+};
+
+const LITERAL: u8 = 0;
+const RLE_8: u8 = 1;
+const RLE_16: u8 = 2;
+const SEQUENCE: u8 = 3;
+const COPY: u8 = 4;
+const REVERSE_BITS_COPY: u8 = 5;
+const BACKWARDS_COPY: u8 = 6;
+const LONG_COMMAND: u8 = 7;
+
+const END: u8 = 0xFF;
+
+const MAX_SHORT_LENGTH: usize = 0x20;
+const MAX_LENGTH: usize = 0x400;
+
+// Back-references can't copy from past this position
+const MAX_POSITION: usize = 0xFFFF;
+
+// Limits the number of positions checked for each kind of back-reference,
+// since long runs of the same bytes would make the search quadratic
+const MAX_CANDIDATES: usize = 0x100;
+
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data up to
+/// and including the 0xFF byte that ends it
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let mut ret = Vec::new();
+    let compressed_size = decompress_to(bytes, &mut ret)?;
+
+    Ok((ret.into_boxed_slice(), compressed_size))
+}
+
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let mut output = utils::SliceOutput::new(dst);
+    decompress_to(bytes, &mut output)?;
+
+    Ok(output.len())
+}
+
+fn decompress_to<O: Output>(bytes: &[u8], ret: &mut O) -> Result<usize, Crunch64Error> {
+    let mut input_pos = 0;
+
+    loop {
+        let byte = utils::next_u8(bytes, &mut input_pos)?;
+        if byte == END {
+            break;
+        }
+
+        let (command, length) = if byte >> 5 == LONG_COMMAND {
+            let next = utils::next_u8(bytes, &mut input_pos)? as usize;
+            ((byte >> 2) & 0x7, ((byte as usize & 0x3) << 8 | next) + 1)
+        } else {
+            (byte >> 5, (byte as usize & 0x1F) + 1)
+        };
+
+        match command {
+            LITERAL => {
+                let literals = bytes
+                    .get(input_pos..input_pos + length)
+                    .ok_or(Crunch64Error::TruncatedInput)?;
+                ret.extend_from_slice(literals)?;
+                input_pos += length;
+            }
+            RLE_8 => {
+                let byte = utils::next_u8(bytes, &mut input_pos)?;
+                for _ in 0..length {
+                    ret.push(byte)?;
+                }
+            }
+            RLE_16 => {
+                let pair = [
+                    utils::next_u8(bytes, &mut input_pos)?,
+                    utils::next_u8(bytes, &mut input_pos)?,
+                ];
+                for _ in 0..length {
+                    ret.extend_from_slice(&pair)?;
+                }
+            }
+            SEQUENCE => {
+                let mut byte = utils::next_u8(bytes, &mut input_pos)?;
+                for _ in 0..length {
+                    ret.push(byte)?;
+                    byte = byte.wrapping_add(1);
+                }
+            }
+            COPY | REVERSE_BITS_COPY | BACKWARDS_COPY => {
+                let position = u16::from_be_bytes([
+                    utils::next_u8(bytes, &mut input_pos)?,
+                    utils::next_u8(bytes, &mut input_pos)?,
+                ]) as usize;
+
+                for i in 0..length {
+                    let source = if command == BACKWARDS_COPY {
+                        position.checked_sub(i)
+                    } else {
+                        Some(position + i)
+                    };
+                    let byte = source
+                        .and_then(|source| ret.written().get(source))
+                        .copied()
+                        .ok_or(Crunch64Error::InvalidBackReference)?;
+
+                    if command == REVERSE_BITS_COPY {
+                        ret.push(byte.reverse_bits())?;
+                    } else {
+                        ret.push(byte)?;
+                    }
+                }
+            }
+            _ => return Err(Crunch64Error::InvalidCommand),
+        }
+    }
+
+    Ok(input_pos)
+}
+
+fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
+    // Literals take up to 2 extra bytes for every 0x400 bytes. Other commands
+    // are only used if they save enough to pay for splitting the literals
+    // around them, plus the 0xFF at the end.
+    Ok(input_size + 2 * (input_size / MAX_LENGTH) + 3)
+}
+
+fn write_command(output: &mut Vec<u8>, command: u8, length: usize) {
+    if length <= MAX_SHORT_LENGTH {
+        output.push(command << 5 | (length - 1) as u8);
+    } else {
+        output.extend([
+            LONG_COMMAND << 5 | command << 2 | ((length - 1) >> 8) as u8,
+            (length - 1) as u8,
+        ]);
+    }
+}
+
+// Writes the pending literals, if any
+fn flush_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LENGTH) {
+        write_command(output, LITERAL, chunk.len());
+        output.extend(chunk);
+    }
+}
+
+/// A command other than literals, chosen by the compressor
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Step {
+    Rle8 {
+        byte: u8,
+        length: usize,
+    },
+    Rle16 {
+        pair: [u8; 2],
+        length: usize,
+    },
+    Sequence {
+        start: u8,
+        length: usize,
+    },
+    Copy {
+        command: u8,
+        position: usize,
+        length: usize,
+    },
+}
+
+impl Step {
+    // Number of input bytes the command produces
+    fn covered(self) -> usize {
+        match self {
+            Step::Rle16 { length, .. } => 2 * length,
+            Step::Rle8 { length, .. }
+            | Step::Sequence { length, .. }
+            | Step::Copy { length, .. } => length,
+        }
+    }
+
+    // Number of bytes the command takes in the compressed data
+    fn cost(self) -> usize {
+        let (length, arguments) = match self {
+            Step::Rle8 { length, .. } | Step::Sequence { length, .. } => (length, 1),
+            Step::Rle16 { length, .. } | Step::Copy { length, .. } => (length, 2),
+        };
+
+        if length <= MAX_SHORT_LENGTH {
+            1 + arguments
+        } else {
+            2 + arguments
+        }
+    }
+
+    fn write(self, output: &mut Vec<u8>) {
+        match self {
+            Step::Rle8 { byte, length } => {
+                write_command(output, RLE_8, length);
+                output.push(byte);
+            }
+            Step::Rle16 { pair, length } => {
+                write_command(output, RLE_16, length);
+                output.extend(pair);
+            }
+            Step::Sequence { start, length } => {
+                write_command(output, SEQUENCE, length);
+                output.push(start);
+            }
+            Step::Copy {
+                command,
+                position,
+                length,
+            } => {
+                write_command(output, command, length);
+                output.extend((position as u16).to_be_bytes());
+            }
+        }
+    }
+}
+
+// Finds the back-references available at each input position. Each kind of
+// copy keeps the positions it can copy from, indexed by the first two bytes
+// it would produce from them.
+struct Finder<'a> {
+    input: &'a [u8],
+    copy: Vec<Vec<u16>>,
+    reverse_bits_copy: Vec<Vec<u16>>,
+    backwards_copy: Vec<Vec<u16>>,
+    // Positions before this one have been indexed
+    indexed: usize,
+}
+
+impl<'a> Finder<'a> {
+    fn new(input: &'a [u8]) -> Finder<'a> {
+        Finder {
+            input,
+            copy: vec![Vec::new(); 0x10000],
+            reverse_bits_copy: vec![Vec::new(); 0x10000],
+            backwards_copy: vec![Vec::new(); 0x10000],
+            indexed: 0,
+        }
+    }
+
+    fn key(a: u8, b: u8) -> usize {
+        u16::from_be_bytes([a, b]) as usize
+    }
+
+    // Indexes the positions before `input_pos`
+    fn advance(&mut self, input_pos: usize) {
+        let input = self.input;
+        while self.indexed < input_pos.min(MAX_POSITION + 1) {
+            let pos = self.indexed;
+            // Positions are only indexed once the byte after them is known
+            let (current, next) = (input[pos], input[pos + 1]);
+
+            self.copy[Self::key(current, next)].push(pos as u16);
+            self.reverse_bits_copy[Self::key(current.reverse_bits(), next.reverse_bits())]
+                .push(pos as u16);
+            if pos > 0 {
+                self.backwards_copy[Self::key(current, input[pos - 1])].push(pos as u16);
+            }
+
+            self.indexed += 1;
+        }
+    }
+
+    // Returns the longest back-reference at `input_pos`, if any
+    fn search(&mut self, input_pos: usize) -> Option<Step> {
+        let input = self.input;
+        if input_pos + 1 >= input.len() {
+            return None;
+        }
+        self.advance(input_pos);
+
+        let max_length = MAX_LENGTH.min(input.len() - input_pos);
+        let key = Self::key(input[input_pos], input[input_pos + 1]);
+        let mut best: Option<Step> = None;
+
+        for (command, chain) in [
+            (COPY, &self.copy[key]),
+            (REVERSE_BITS_COPY, &self.reverse_bits_copy[key]),
+            (BACKWARDS_COPY, &self.backwards_copy[key]),
+        ] {
+            for &position in chain.iter().rev().take(MAX_CANDIDATES) {
+                let position = position as usize;
+                let length = (0..max_length)
+                    .take_while(|&i| {
+                        let byte = match command {
+                            COPY => input[position + i],
+                            REVERSE_BITS_COPY => input[position + i].reverse_bits(),
+                            _ if i <= position => input[position - i],
+                            _ => return false,
+                        };
+                        byte == input[input_pos + i]
+                    })
+                    .count();
+
+                if best.map_or(true, |best| length > best.covered()) {
+                    best = Some(Step::Copy {
+                        command,
+                        position,
+                        length,
+                    });
+                    if length == max_length {
+                        return best;
+                    }
+                }
+            }
+        }
+
+        best
+    }
+}
+
+// Returns the run-length commands that can be used at `input_pos`
+fn runs(input: &[u8], input_pos: usize) -> [Option<Step>; 3] {
+    let rest = &input[input_pos..];
+    let byte = rest[0];
+
+    let rle_8 = rest
+        .iter()
+        .take(MAX_LENGTH)
+        .take_while(|&&b| b == byte)
+        .count();
+
+    let sequence = rest
+        .iter()
+        .take(MAX_LENGTH)
+        .enumerate()
+        .take_while(|&(i, &b)| b == byte.wrapping_add(i as u8))
+        .count();
+
+    let rle_16 = rest.get(..2).map(|pair| Step::Rle16 {
+        pair: [pair[0], pair[1]],
+        length: rest
+            .chunks_exact(2)
+            .take(MAX_LENGTH)
+            .take_while(|&chunk| chunk == pair)
+            .count(),
+    });
+
+    [
+        Some(Step::Rle8 {
+            byte,
+            length: rle_8,
+        }),
+        rle_16,
+        Some(Step::Sequence {
+            start: byte,
+            length: sequence,
+        }),
+    ]
+}
+
+/// Compresses `bytes` greedily, using whichever command produces the most
+/// bytes at each position. The output isn't known to match HAL's encoder.
+pub fn compress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    let mut output = Vec::with_capacity(size_for_compressed_buffer(bytes.len())?);
+
+    let mut finder = Finder::new(bytes);
+    let mut literals_start = 0;
+    let mut input_pos = 0;
+
+    while input_pos < bytes.len() {
+        let runs = runs(bytes, input_pos);
+        let best = runs
+            .into_iter()
+            .chain([finder.search(input_pos)])
+            .flatten()
+            .max_by_key(|step| (step.covered(), core::cmp::Reverse(step.cost())));
+
+        // Commands have to save at least 2 bytes over literals, which is what
+        // splitting the literals around them can cost
+        match best {
+            Some(step) if step.covered() >= step.cost() + 2 => {
+                flush_literals(&mut output, &bytes[literals_start..input_pos]);
+                step.write(&mut output);
+                input_pos += step.covered();
+                literals_start = input_pos;
+            }
+            _ => input_pos += 1,
+        }
+    }
+    flush_literals(&mut output, &bytes[literals_start..]);
+    output.push(END);
+
+    Ok(output.into_boxed_slice())
+}
+
+/// HAL LZ [`Codec`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HalLz;
+
+impl HalLz {
+    pub const DEFAULT: HalLz = HalLz;
+}
+
+impl Codec for HalLz {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress(bytes)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        // HAL LZ doesn't store the uncompressed size, so the only way to know
+        // it is to decompress the data
+        Ok(decompress(bytes)?.len())
+    }
+
+    fn header_size(&self, _bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        // HAL LZ data doesn't have a header
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use crate::Crunch64Error;
+    use rstest::rstest;
+    use std::{fs, path::PathBuf};
+
+    // The test files were made by this encoder, not taken from real data
+    #[rstest]
+    fn test_decompression_regression(
+        #[files("../test_data/*.hal-lz")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let decompressed = super::decompress(&compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_compression_regression(
+        #[files("../test_data/*.hal-lz")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let compressed = super::compress(&decompressed_file)?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_commands() -> Result<(), Crunch64Error> {
+        let bytes = [
+            0x02, b'a', b'b', b'c', // Literals "abc"
+            0x22, b'x', // "x" repeated 3 times
+            0x41, b'-', b'=', // "-=" repeated 2 times
+            0x63, b'0', // "0123"
+            0x81, 0x00, 0x01, // "bc" from position 1
+            0xA0, 0x00, 0x00, // "a" with its bits reversed
+            0xC2, 0x00, 0x02, // "cba" from position 2
+            0xE4, 0x22, b'z', // "z" repeated 0x23 times
+            0xFF,
+        ];
+
+        let mut expected = b"abcxxx-=-=0123bc\x86cba".to_vec();
+        expected.extend([b'z'; 0x23]);
+        assert_eq!(super::decompress(&bytes)?.as_ref(), expected);
+        assert_eq!(
+            super::decompress_with_size(&[0x00, b'a', 0xFF, 0xAA])?,
+            (b"a".to_vec().into_boxed_slice(), 3)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_errors() {
+        // No end command
+        assert_eq!(
+            super::decompress(&[0x01, b'a', b'b']),
+            Err(Crunch64Error::TruncatedInput)
+        );
+
+        // Long command 7
+        assert_eq!(
+            super::decompress(&[0xFC, 0x00, 0xFF]),
+            Err(Crunch64Error::InvalidCommand)
+        );
+
+        // Copies from positions that haven't been written yet
+        assert_eq!(
+            super::decompress(&[0x00, b'a', 0x80, 0x00, 0x01, 0xFF]),
+            Err(Crunch64Error::InvalidBackReference)
+        );
+        assert_eq!(
+            super::decompress(&[0x00, b'a', 0xC1, 0x00, 0x00, 0xFF]),
+            Err(Crunch64Error::InvalidBackReference)
+        );
+    }
+
+    #[test]
+    fn test_corrupt_input() {
+        let compressed_file = fs::read("../test_data/small.txt.hal-lz").unwrap();
+
+        // HAL LZ isn't a `Format`, so the generic corrupt input test skips it
+        for len in 0..compressed_file.len() {
+            let _ = super::decompress(&compressed_file[..len]);
+        }
+        for i in 0..compressed_file.len() {
+            for mask in [0x01, 0x10, 0x80, 0xFF] {
+                let mut corrupted = compressed_file.clone();
+                corrupted[i] ^= mask;
+                let _ = super::decompress(&corrupted);
+            }
+        }
+    }
+
+    #[rstest]
+    fn test_compression_commands(
+        #[values(
+            b"abcdefgh".repeat(0x30),
+            [0x55; 0x500].to_vec(),
+            b"\x12\x34".repeat(0x300),
+            (0..=0xFF).cycle().take(0x500).collect::<Vec<u8>>(),
+            b"abcdefgh".iter().map(|b| b.reverse_bits()).chain(*b"abcdefgh").collect::<Vec<u8>>(),
+            b"abcdefgh".iter().chain(b"abcdefgh".iter().rev()).copied().collect::<Vec<u8>>()
+        )]
+        data: Vec<u8>,
+    ) -> Result<(), Crunch64Error> {
+        let compressed = super::compress(&data)?;
+        assert!(compressed.len() < data.len());
+        assert_eq!(super::decompress(&compressed)?.as_ref(), data);
+        Ok(())
+    }
+}
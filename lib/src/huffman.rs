@@ -0,0 +1,788 @@
+// Implements the Huffman compression of the GBA/DS BIOS. After the same header
+// as LZ77 with type 0x24 (4-bit symbols) or 0x28 (8-bit symbols), a byte gives
+// the size of the tree table as a number of 2-byte pairs minus 1. The root node
+// follows it, and the data starts right after the table as 32-bit
+// little-endian words read from the most significant bit down.
+//
+// Each node byte stores in its low 6 bits the offset from its own pair to the
+// pair holding its two children, minus 1. Bit 7 tells whether the child for a
+// 0 bit is a leaf and bit 6 the same for the child for a 1 bit, in which case
+// the byte in the table is the symbol. 4-bit data stores the low nibble of
+// each byte first.
+
+use alloc::{boxed::Box, collections::BinaryHeap, vec, vec::Vec};
+use core::cmp::Reverse;
+
+use crate::{utils, Codec, Crunch64Error};
+
+// Children can be at most 0x40 pairs after their parent
+const MAX_NODE_OFFSET: usize = 0x3F;
+
+/// Size of the symbols encoded in the data, stored in the first byte of the
+/// header
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DataSize {
+    /// Type 0x24, encoding each byte as two nibbles
+    Four,
+    /// Type 0x28
+    #[default]
+    Eight,
+}
+
+impl DataSize {
+    /// Lowercase name of the data size
+    pub fn name(self) -> &'static str {
+        match self {
+            DataSize::Four => "4-bit",
+            DataSize::Eight => "8-bit",
+        }
+    }
+
+    fn bits(self) -> usize {
+        match self {
+            DataSize::Four => 4,
+            DataSize::Eight => 8,
+        }
+    }
+}
+
+/// Fields of the Huffman header, 4 bytes long or 8 bytes if the size doesn't
+/// fit in 24 bits. The tree table isn't part of the header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HuffmanHeader {
+    pub data_size: DataSize,
+    pub uncompressed_size: usize,
+}
+
+impl HuffmanHeader {
+    pub fn parse(bytes: &[u8]) -> Result<HuffmanHeader, Crunch64Error> {
+        let data_size = match bytes.first() {
+            Some(0x24) => DataSize::Four,
+            Some(0x28) => DataSize::Eight,
+            _ => return Err(Crunch64Error::InvalidHuffmanHeader),
+        };
+
+        let (uncompressed_size, _) =
+            utils::parse_bios_header_size(bytes).ok_or(Crunch64Error::InvalidHuffmanHeader)?;
+
+        Ok(HuffmanHeader {
+            data_size,
+            uncompressed_size,
+        })
+    }
+
+    /// Size of the header in bytes
+    pub fn size(&self) -> usize {
+        utils::bios_header_size(self.uncompressed_size)
+    }
+
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        let kind = 0x20 | self.data_size.bits() as u8;
+        utils::write_bios_header(dst, kind, self.uncompressed_size);
+    }
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data,
+/// which can be followed by unrelated data in `bytes`
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let header = HuffmanHeader::parse(bytes)?;
+
+    let mut ret = utils::decompression_buffer(header.uncompressed_size, bytes.len());
+    let compressed_size = decompress_to(bytes, &header, &mut ret)?;
+
+    Ok((ret.into_boxed_slice(), compressed_size))
+}
+
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let header = HuffmanHeader::parse(bytes)?;
+
+    let dst = dst
+        .get_mut(..header.uncompressed_size)
+        .ok_or(Crunch64Error::OutOfBounds)?;
+    decompress_to(bytes, &header, &mut utils::SliceOutput::new(dst))?;
+
+    Ok(header.uncompressed_size)
+}
+
+fn decompress_to<O: utils::Output>(
+    bytes: &[u8],
+    header: &HuffmanHeader,
+    ret: &mut O,
+) -> Result<usize, Crunch64Error> {
+    let decompressed_size = header.uncompressed_size;
+    let tree_start = header.size();
+
+    let tree_size = (*bytes.get(tree_start).ok_or(Crunch64Error::TruncatedInput)? as usize + 1) * 2;
+    let tree = bytes
+        .get(tree_start..tree_start + tree_size)
+        .ok_or(Crunch64Error::TruncatedInput)?;
+    let mut input_pos = tree_start + tree_size;
+
+    // Position in the table of the current node, starting from the root
+    let mut node_pos = 1;
+    let mut low_nibble = None;
+
+    while ret.len() < decompressed_size {
+        let word = bytes
+            .get(input_pos..input_pos + 4)
+            .ok_or(Crunch64Error::TruncatedInput)?;
+        let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        input_pos += 4;
+
+        for bit in (0..32).rev() {
+            let node = tree[node_pos] as usize;
+            let direction = (word >> bit) as usize & 1;
+            let child_pos = (node_pos & !1) + (node & MAX_NODE_OFFSET) * 2 + 2 + direction;
+            // A node pointing outside the table means the tree itself is corrupt
+            let child = *tree
+                .get(child_pos)
+                .ok_or(Crunch64Error::InvalidHuffmanHeader)?;
+
+            if node & (0x80 >> direction) == 0 {
+                node_pos = child_pos;
+                continue;
+            }
+            node_pos = 1;
+
+            match header.data_size {
+                DataSize::Eight => ret.push(child)?,
+                DataSize::Four => match low_nibble.take() {
+                    Some(low) => ret.push(low | child << 4)?,
+                    None => low_nibble = Some(child & 0xF),
+                },
+            }
+
+            if ret.len() >= decompressed_size {
+                break;
+            }
+        }
+    }
+
+    Ok(input_pos)
+}
+
+fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
+    // A Huffman code never takes more bits than a fixed-size one, so the data
+    // is at most as large as the input, after the header and a full table
+    Ok(8 + 0x200 + input_size.next_multiple_of(4))
+}
+
+/// Options for [`compress_with_options`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CompressOptions {
+    pub data_size: DataSize,
+}
+
+impl CompressOptions {
+    pub const DEFAULT: CompressOptions = CompressOptions {
+        data_size: DataSize::Eight,
+    };
+}
+
+impl Default for CompressOptions {
+    fn default() -> CompressOptions {
+        CompressOptions::DEFAULT
+    }
+}
+
+pub fn compress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    compress_with_options(bytes, &CompressOptions::DEFAULT)
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Node {
+    Leaf(u8),
+    Branch(usize, usize),
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    // Builds a Huffman tree for the symbols with a non-zero frequency. The root
+    // is always a branch, so a single symbol gets a leaf on both sides.
+    fn from_frequencies(frequencies: &[usize]) -> Tree {
+        let mut nodes = Vec::new();
+        let mut heap = BinaryHeap::new();
+
+        for (symbol, &frequency) in frequencies.iter().enumerate() {
+            if frequency > 0 {
+                nodes.push(Node::Leaf(symbol as u8));
+                heap.push(Reverse((frequency, nodes.len() - 1)));
+            }
+        }
+
+        if heap.len() < 2 {
+            if nodes.is_empty() {
+                nodes.push(Node::Leaf(0));
+            }
+            nodes.push(Node::Branch(0, 0));
+            return Tree { nodes };
+        }
+
+        while heap.len() > 1 {
+            let Reverse((zero_frequency, zero)) = heap.pop().unwrap();
+            let Reverse((one_frequency, one)) = heap.pop().unwrap();
+            nodes.push(Node::Branch(zero, one));
+            heap.push(Reverse((zero_frequency + one_frequency, nodes.len() - 1)));
+        }
+
+        Tree { nodes }
+    }
+
+    fn root(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    // The path from the root to each symbol, as 0 and 1 bits
+    fn codes(&self, symbol_count: usize) -> Vec<Vec<bool>> {
+        let mut codes = vec![Vec::new(); symbol_count];
+        let mut pending = vec![(self.root(), Vec::new())];
+
+        while let Some((index, code)) = pending.pop() {
+            match self.nodes[index] {
+                Node::Leaf(symbol) => {
+                    // Keep the shortest path when both children of the root
+                    // are the same leaf
+                    if codes[symbol as usize].is_empty() {
+                        codes[symbol as usize] = code;
+                    }
+                }
+                Node::Branch(zero, one) => {
+                    let mut one_code = code.clone();
+                    one_code.push(true);
+                    pending.push((one, one_code));
+
+                    let mut zero_code = code;
+                    zero_code.push(false);
+                    pending.push((zero, zero_code));
+                }
+            }
+        }
+
+        codes
+    }
+
+    /// Lays out the tree in the table format the BIOS expects, with the size
+    /// byte and the root first and padded so that the data is word-aligned.
+    ///
+    /// The children of each branch are placed in the next free pair. Placing
+    /// the most recent branch first keeps subtrees together, but whenever that
+    /// would leave another pending branch further than 0x40 pairs from its
+    /// children, the one closest to that limit goes first instead.
+    fn write(&self, dst: &mut Vec<u8>) -> Result<(), Crunch64Error> {
+        let branch_count = self
+            .nodes
+            .iter()
+            .filter(|node| matches!(node, Node::Branch(..)))
+            .count();
+        let pair_count = (branch_count + 1).next_multiple_of(2);
+
+        let mut table = vec![0; pair_count * 2];
+        table[0] = (pair_count - 1) as u8;
+
+        // Position in the table of each branch still waiting for its children
+        let mut pending = vec![(1, self.root())];
+        let last_pair = |position: usize| position / 2 + MAX_NODE_OFFSET + 1;
+
+        for pair in 1..=branch_count {
+            let mut deadlines: Vec<usize> = pending[..pending.len() - 1]
+                .iter()
+                .map(|&(position, _)| last_pair(position))
+                .collect();
+            deadlines.sort_unstable();
+            let can_take_last = deadlines
+                .iter()
+                .enumerate()
+                .all(|(i, &deadline)| deadline > pair + i);
+
+            let index = if can_take_last {
+                pending.len() - 1
+            } else {
+                (0..pending.len())
+                    .min_by_key(|&i| last_pair(pending[i].0))
+                    .unwrap()
+            };
+            let (position, branch) = pending.remove(index);
+
+            // Not seen with 256 leaves or fewer, see `test_tree_layout`
+            let offset = pair - position / 2 - 1;
+            if offset > MAX_NODE_OFFSET {
+                return Err(Crunch64Error::OutOfBounds);
+            }
+
+            let Node::Branch(zero, one) = self.nodes[branch] else {
+                unreachable!();
+            };
+            let mut node = offset as u8;
+            for (direction, child) in [zero, one].into_iter().enumerate() {
+                let child_pos = pair * 2 + direction;
+                match self.nodes[child] {
+                    Node::Leaf(symbol) => {
+                        node |= 0x80 >> direction;
+                        table[child_pos] = symbol;
+                    }
+                    Node::Branch(..) => pending.push((child_pos, child)),
+                }
+            }
+            table[position] = node;
+        }
+
+        dst.extend(table);
+        Ok(())
+    }
+}
+
+struct WordWriter {
+    output: Vec<u8>,
+    word: u32,
+    bit_count: usize,
+}
+
+impl WordWriter {
+    fn write_bit(&mut self, bit: bool) {
+        self.word = self.word << 1 | bit as u32;
+        self.bit_count += 1;
+
+        if self.bit_count == 32 {
+            self.output.extend(self.word.to_le_bytes());
+            self.word = 0;
+            self.bit_count = 0;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.output
+                .extend((self.word << (32 - self.bit_count)).to_le_bytes());
+        }
+        self.output
+    }
+}
+
+fn symbols(bytes: &[u8], data_size: DataSize) -> impl Iterator<Item = u8> + '_ {
+    bytes
+        .iter()
+        .flat_map(move |&byte| match data_size {
+            DataSize::Four => [Some(byte & 0xF), Some(byte >> 4)],
+            DataSize::Eight => [Some(byte), None],
+        })
+        .flatten()
+}
+
+/// Compresses `bytes` with a Huffman tree built from the frequency of each
+/// symbol. The nodes aren't in the canonical order of Nintendo's encoder, so
+/// the output won't match it byte for byte.
+pub fn compress_with_options(
+    bytes: &[u8],
+    options: &CompressOptions,
+) -> Result<Box<[u8]>, Crunch64Error> {
+    let symbol_count = 1 << options.data_size.bits();
+
+    let mut frequencies = vec![0; symbol_count];
+    for symbol in symbols(bytes, options.data_size) {
+        frequencies[symbol as usize] += 1;
+    }
+    let tree = Tree::from_frequencies(&frequencies);
+    compress_with_tree(bytes, options.data_size, &tree)
+}
+
+fn compress_with_tree(
+    bytes: &[u8],
+    data_size: DataSize,
+    tree: &Tree,
+) -> Result<Box<[u8]>, Crunch64Error> {
+    let codes = tree.codes(1 << data_size.bits());
+
+    let mut output = Vec::with_capacity(size_for_compressed_buffer(bytes.len())?);
+    HuffmanHeader {
+        data_size,
+        uncompressed_size: bytes.len(),
+    }
+    .write(&mut output);
+    tree.write(&mut output)?;
+
+    let mut writer = WordWriter {
+        output,
+        word: 0,
+        bit_count: 0,
+    };
+    for symbol in symbols(bytes, data_size) {
+        for &bit in &codes[symbol as usize] {
+            writer.write_bit(bit);
+        }
+    }
+
+    Ok(writer.finish().into_boxed_slice())
+}
+
+/// Huffman [`Codec`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Huffman {
+    pub options: CompressOptions,
+}
+
+impl Huffman {
+    pub const DEFAULT: Huffman = Huffman {
+        options: CompressOptions::DEFAULT,
+    };
+}
+
+impl Codec for Huffman {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress_with_options(bytes, &self.options)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Ok(HuffmanHeader::parse(bytes)?.uncompressed_size)
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Ok(HuffmanHeader::parse(bytes)?.size())
+    }
+}
+
+#[cfg(feature = "c_bindings")]
+mod c_bindings {
+    #[no_mangle]
+    pub extern "C" fn crunch64_huffman_decompress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::HuffmanHeader::parse(bytes) {
+            Err(e) => return e,
+            Ok(header) => unsafe { *dst_size = header.uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_huffman_decompress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_huffman_compress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        match super::size_for_compressed_buffer(src_len) {
+            Err(e) => return e,
+            Ok(uncompressed_size) => unsafe { *dst_size = uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_huffman_compress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+        four_bit: bool,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let options = super::CompressOptions {
+            data_size: if four_bit {
+                super::DataSize::Four
+            } else {
+                super::DataSize::Eight
+            },
+        };
+        let data = match super::compress_with_options(bytes, &options) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+}
+
+#[cfg(feature = "python_bindings")]
+pub(crate) mod python_bindings {
+    use pyo3::prelude::*;
+    use std::borrow::Cow;
+
+    /**
+     * We use a `Cow` instead of a plain &[u8] because the latter only allows Python's
+     * `bytes` objects, while `Cow`` allows for both `bytes` and `bytearray`.
+     * This is important because an argument typed as `bytes` allows to pass a
+     * `bytearray` object too.
+     */
+
+    #[pyfunction]
+    pub(crate) fn decompress_huffman(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::decompress(&bytes)?.into()))
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (bytes, four_bit=false))]
+    pub(crate) fn compress_huffman(
+        bytes: Cow<[u8]>,
+        four_bit: bool,
+    ) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        let options = super::CompressOptions {
+            data_size: if four_bit {
+                super::DataSize::Four
+            } else {
+                super::DataSize::Eight
+            },
+        };
+        Ok(Cow::Owned(
+            super::compress_with_options(&bytes, &options)?.into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use super::{CompressOptions, DataSize, HuffmanHeader, Node, Tree};
+    use crate::Crunch64Error;
+    use rstest::rstest;
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    fn options_for(path: &Path) -> CompressOptions {
+        CompressOptions {
+            data_size: if path.extension().unwrap() == "huffman4" {
+                DataSize::Four
+            } else {
+                DataSize::Eight
+            },
+        }
+    }
+
+    // The test files were made by this encoder, not taken from real data
+    #[rstest]
+    fn test_decompression_regression(
+        #[files("../test_data/*.huffman4")]
+        #[files("../test_data/*.huffman8")]
+        path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let decompressed = super::decompress(&compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_compression_regression(
+        #[files("../test_data/*.huffman4")]
+        #[files("../test_data/*.huffman8")]
+        path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let compressed = super::compress_with_options(&decompressed_file, &options_for(&path))?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_header_cycle(
+        #[values(DataSize::Four, DataSize::Eight)] data_size: DataSize,
+        #[values(0, 1, 0xFFFFFF, 0x1000000)] uncompressed_size: usize,
+    ) -> Result<(), Crunch64Error> {
+        let header = HuffmanHeader {
+            data_size,
+            uncompressed_size,
+        };
+
+        let mut bytes = Vec::new();
+        header.write(&mut bytes);
+        assert_eq!(bytes.len(), header.size());
+        assert_eq!(HuffmanHeader::parse(&bytes)?, header);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::empty(Vec::new())]
+    #[case::single_symbol(vec![0x5A; 0x100])]
+    #[case::all_bytes((0..=255).collect())]
+    // Frequencies following the Fibonacci sequence give the deepest tree, with
+    // a leaf on every level
+    #[case::deep_tree({
+        let (mut a, mut b) = (1, 1);
+        let mut data = Vec::new();
+        for byte in 0..28u8 {
+            data.extend(vec![byte; a]);
+            (a, b) = (b, a + b);
+        }
+        data
+    })]
+    // Pseudo-random frequencies for every byte value
+    #[case::varied_tree({
+        let mut data = Vec::new();
+        let mut state = 1u32;
+        for byte in 0..=255u8 {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            data.extend(vec![byte; 1 + (state >> 16) as usize % 0x40]);
+        }
+        data
+    })]
+    fn test_cycle(
+        #[case] data: Vec<u8>,
+        #[values(DataSize::Four, DataSize::Eight)] data_size: DataSize,
+    ) -> Result<(), Crunch64Error> {
+        let compressed = super::compress_with_options(&data, &CompressOptions { data_size })?;
+        assert!(compressed.len() <= super::size_for_compressed_buffer(data.len())?);
+        assert_eq!(compressed.len() % 4, 0);
+        assert_eq!(super::decompress(&compressed)?.as_ref(), data);
+        Ok(())
+    }
+
+    // Builds a tree of `leaf_count` leaves by repeatedly merging the two
+    // subtrees picked from the ones left, with new subtrees added at the end
+    fn merged_tree(leaf_count: usize, mut pick: impl FnMut(usize) -> (usize, usize)) -> Tree {
+        let mut nodes: Vec<Node> = (0..leaf_count).map(|i| Node::Leaf(i as u8)).collect();
+        let mut subtrees: Vec<usize> = (0..leaf_count).collect();
+        while subtrees.len() > 1 {
+            let (zero, one) = pick(subtrees.len());
+            let (zero, one) = (subtrees[zero], subtrees[one]);
+            subtrees.retain(|&subtree| subtree != zero && subtree != one);
+            nodes.push(Node::Branch(zero, one));
+            subtrees.push(nodes.len() - 1);
+        }
+        Tree { nodes }
+    }
+
+    #[test]
+    fn test_tree_layout() -> Result<(), Crunch64Error> {
+        // The widest tree, with every leaf 8 levels deep
+        let mut trees = vec![merged_tree(0x100, |_| (0, 1))];
+        // The deepest tree, with a leaf on every level
+        trees.push(merged_tree(0x100, |count| (0, count - 1)));
+        // Random shapes, which can keep many branches waiting for their
+        // children at once
+        let mut state = 1u32;
+        let mut random = move |count: usize| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as usize % count
+        };
+        for _ in 0..0x400 {
+            trees.push(merged_tree(0x100, |count| {
+                let zero = random(count);
+                (zero, (zero + 1 + random(count - 1)) % count)
+            }));
+        }
+
+        let data: Vec<u8> = (0..=255).collect();
+        for tree in trees {
+            let compressed = super::compress_with_tree(&data, DataSize::Eight, &tree)?;
+            assert_eq!(super::decompress(&compressed)?.as_ref(), data);
+        }
+
+        // Too many branches are waiting for their children to fit them all
+        // within reach, which takes more leaves than there are symbols
+        let mut bytes = Vec::new();
+        assert_eq!(
+            merged_tree(0x1000, |_| (0, 1)).write(&mut bytes),
+            Err(Crunch64Error::OutOfBounds)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_errors() {
+        assert_eq!(
+            super::decompress(&[0x20, 0x01, 0x00, 0x00, 0x01, 0xC0, 0x00, 0x01]),
+            Err(Crunch64Error::InvalidHuffmanHeader)
+        );
+
+        // The root points past the end of the table
+        assert_eq!(
+            super::decompress(&[
+                0x28, 0x01, 0x00, 0x00, 0x01, 0xC1, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00
+            ]),
+            Err(Crunch64Error::InvalidHuffmanHeader)
+        );
+
+        assert_eq!(
+            super::decompress(&[0x28, 0x01, 0x00, 0x00, 0x01, 0xC0, 0x00, 0x01]),
+            Err(Crunch64Error::TruncatedInput)
+        );
+    }
+}
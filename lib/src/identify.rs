@@ -0,0 +1,310 @@
+//! Finding the encoder settings that produced some compressed data.
+//!
+//! [`identify`] decompresses the data, compresses it again with every
+//! [`Encoder`] of its format and compares the results with the original.
+
+use alloc::vec::Vec;
+use core::{cmp, fmt};
+
+use crate::{
+    gzip, huffman, lz77, lzkn64, mio0, rare, rle, utils, vpk0, yay0, yaz0, zlib, Codec,
+    Crunch64Error, Format,
+};
+
+/// Compression levels supported by the DEFLATE-based formats
+const LEVELS: core::ops::RangeInclusive<usize> = 1..=9;
+
+/// A format together with the options to compress it with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Encoder {
+    Yay0(yay0::Yay0),
+    Yaz0(yaz0::Yaz0),
+    Mio0(mio0::Mio0),
+    Gzip(gzip::Gzip),
+    Zlib(zlib::Zlib),
+    Deflate(gzip::Deflate),
+    Rare(rare::Rare),
+    Vpk0(vpk0::Vpk0),
+    Lzkn64(lzkn64::Lzkn64),
+    Lz77(lz77::Lz77),
+    Rle(rle::Rle),
+    Huffman(huffman::Huffman),
+}
+
+impl Encoder {
+    pub fn format(&self) -> Format {
+        match self {
+            Encoder::Yay0(_) => Format::Yay0,
+            Encoder::Yaz0(_) => Format::Yaz0,
+            Encoder::Mio0(_) => Format::Mio0,
+            Encoder::Gzip(_) => Format::Gzip,
+            Encoder::Zlib(_) => Format::Zlib,
+            Encoder::Deflate(_) => Format::Deflate,
+            Encoder::Rare(_) => Format::Rare,
+            Encoder::Vpk0(_) => Format::Vpk0,
+            Encoder::Lzkn64(_) => Format::Lzkn64,
+            Encoder::Lz77(_) => Format::Lz77,
+            Encoder::Rle(_) => Format::Rle,
+            Encoder::Huffman(_) => Format::Huffman,
+        }
+    }
+
+    pub fn codec(&self) -> &dyn Codec {
+        match self {
+            Encoder::Yay0(codec) => codec,
+            Encoder::Yaz0(codec) => codec,
+            Encoder::Mio0(codec) => codec,
+            Encoder::Gzip(codec) => codec,
+            Encoder::Zlib(codec) => codec,
+            Encoder::Deflate(codec) => codec,
+            Encoder::Rare(codec) => codec,
+            Encoder::Vpk0(codec) => codec,
+            Encoder::Lzkn64(codec) => codec,
+            Encoder::Lz77(codec) => codec,
+            Encoder::Rle(codec) => codec,
+            Encoder::Huffman(codec) => codec,
+        }
+    }
+
+    /// Every encoder that [`identify`] tries for `format`.
+    ///
+    /// Settings stored in the header, like the Yaz0 alignment, the gzip file
+    /// name, the vpk0 method, the LZ77 variant or the Huffman data size, are copied from `bytes`.
+    pub fn all(format: Format, bytes: &[u8]) -> Vec<Encoder> {
+        let levels = || LEVELS.flat_map(|level| [false, true].map(|small_mem| (level, small_mem)));
+
+        match format {
+            Format::Yay0 => vec![Encoder::Yay0(yay0::Yay0::DEFAULT)],
+            Format::Yaz0 => {
+                let alignment = yaz0::Yaz0Header::parse_lenient(bytes)
+                    .map(|header| header.alignment)
+                    .unwrap_or(0);
+                vec![Encoder::Yaz0(yaz0::Yaz0 {
+                    options: yaz0::CompressOptions {
+                        alignment,
+                        ..Default::default()
+                    },
+                })]
+            }
+            Format::Mio0 => vec![Encoder::Mio0(mio0::Mio0::DEFAULT)],
+            Format::Gzip => {
+                let header = gzip::decompress_with_header(bytes)
+                    .ok()
+                    .and_then(|(_, header)| header);
+                levels()
+                    .map(|(level, small_mem)| {
+                        Encoder::Gzip(gzip::Gzip {
+                            options: gzip::CompressOptions {
+                                level,
+                                small_mem,
+                                header: header.clone(),
+                            },
+                        })
+                    })
+                    .collect()
+            }
+            Format::Zlib => levels()
+                .map(|(level, small_mem)| Encoder::Zlib(zlib::Zlib { level, small_mem }))
+                .collect(),
+            Format::Deflate => levels()
+                .map(|(level, small_mem)| Encoder::Deflate(gzip::Deflate { level, small_mem }))
+                .collect(),
+            Format::Rare => levels()
+                .map(|(level, small_mem)| Encoder::Rare(rare::Rare { level, small_mem }))
+                .collect(),
+            Format::Vpk0 => {
+                let method = vpk0::Vpk0Header::parse(bytes)
+                    .map(|header| header.method)
+                    .unwrap_or_default();
+                vec![Encoder::Vpk0(vpk0::Vpk0 {
+                    options: vpk0::CompressOptions { method },
+                })]
+            }
+            Format::Lzkn64 => vec![Encoder::Lzkn64(lzkn64::Lzkn64::DEFAULT)],
+            Format::Lz77 => {
+                let variant = lz77::Lz77Header::parse(bytes)
+                    .map(|header| header.variant)
+                    .unwrap_or_default();
+                [false, true]
+                    .map(|vram_safe| {
+                        Encoder::Lz77(lz77::Lz77 {
+                            options: lz77::CompressOptions { variant, vram_safe },
+                        })
+                    })
+                    .into()
+            }
+            Format::Rle => vec![Encoder::Rle(rle::Rle::DEFAULT)],
+            Format::Huffman => {
+                let data_size = huffman::HuffmanHeader::parse(bytes)
+                    .map(|header| header.data_size)
+                    .unwrap_or_default();
+                vec![Encoder::Huffman(huffman::Huffman {
+                    options: huffman::CompressOptions { data_size },
+                })]
+            }
+        }
+    }
+}
+
+impl fmt::Display for Encoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (level, small_mem) = match self {
+            Encoder::Yay0(_)
+            | Encoder::Yaz0(_)
+            | Encoder::Mio0(_)
+            | Encoder::Lzkn64(_)
+            | Encoder::Rle(_) => return write!(f, "{}", self.format()),
+            Encoder::Gzip(codec) => (codec.options.level, codec.options.small_mem),
+            Encoder::Zlib(codec) => (codec.level, codec.small_mem),
+            Encoder::Deflate(codec) => (codec.level, codec.small_mem),
+            Encoder::Rare(codec) => (codec.level, codec.small_mem),
+            Encoder::Vpk0(codec) => return write!(f, "vpk0 ({})", codec.options.method.name()),
+            Encoder::Huffman(codec) => {
+                return write!(f, "huffman ({})", codec.options.data_size.name())
+            }
+            Encoder::Lz77(codec) => {
+                write!(f, "lz77 ({}", codec.options.variant.name())?;
+                if codec.options.vram_safe {
+                    write!(f, ", vram-safe")?;
+                }
+                return write!(f, ")");
+            }
+        };
+
+        write!(f, "{} (level {}", self.format(), level)?;
+        if small_mem {
+            write!(f, ", small_mem")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// How close an [`Encoder`] comes to reproducing the original data
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub encoder: Encoder,
+    /// Offset of the first byte that differs from the original data, or
+    /// `None` if the encoder reproduces it exactly. Zero bytes after the end
+    /// of the compressed data are treated as padding.
+    pub first_difference: Option<usize>,
+}
+
+impl Candidate {
+    pub fn is_match(&self) -> bool {
+        self.first_difference.is_none()
+    }
+}
+
+// Compares the re-compressed data with the original
+fn first_difference(original: &[u8], compressed: &[u8]) -> Option<usize> {
+    let common = utils::longest_common_prefix(original, compressed);
+
+    if common == compressed.len() && original[common..].iter().all(|&b| b == 0) {
+        None
+    } else {
+        Some(common)
+    }
+}
+
+/// Compresses the decompressed `bytes` with every [`Encoder`] of each format
+/// that can decompress them, and compares the results with `bytes`.
+///
+/// The candidates are sorted from best to worst: exact matches first, then by
+/// the offset of the first difference. An encoder that can't compress the
+/// data differs at offset 0. Fails with
+/// [`Crunch64Error::UnsupportedCompressionType`] if no format can decompress
+/// `bytes`.
+pub fn identify(bytes: &[u8]) -> Result<Vec<Candidate>, Crunch64Error> {
+    let mut candidates = Vec::new();
+    let mut decompressed_any = false;
+
+    for format in Format::ALL {
+        let Ok(decompressed) = format.decompress(bytes) else {
+            continue;
+        };
+        decompressed_any = true;
+
+        for encoder in Encoder::all(format, bytes) {
+            // An encoder that fails doesn't reproduce any of the data, but
+            // the others can still match
+            let first_difference = match encoder.codec().compress(&decompressed) {
+                Ok(compressed) => first_difference(bytes, &compressed),
+                Err(_) => Some(0),
+            };
+            candidates.push(Candidate {
+                first_difference,
+                encoder,
+            });
+        }
+    }
+
+    if !decompressed_any {
+        return Err(Crunch64Error::UnsupportedCompressionType);
+    }
+
+    candidates
+        .sort_by_key(|candidate| cmp::Reverse(candidate.first_difference.unwrap_or(usize::MAX)));
+    Ok(candidates)
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use rstest::rstest;
+    use std::{fs, path::PathBuf};
+
+    #[rstest]
+    fn test_identify(
+        #[files("../test_data/small.txt.*")]
+        #[files("../test_data/mips_gist_wiseguy_yaz0.bin.*")]
+        #[exclude("\\.hal-lz$")]
+        path: PathBuf,
+    ) {
+        let bytes = fs::read(&path).unwrap();
+        let expected = match path.extension().unwrap().to_str().unwrap() {
+            "Yay0" => "yay0",
+            "Yaz0" => "yaz0",
+            "MIO0" => "mio0",
+            "gz" | "gzip-9" => "gzip (level 9)",
+            "gzip-1" => "gzip (level 1)",
+            "gzip-2" => "gzip (level 2)",
+            "gzip-3" => "gzip (level 3)",
+            "gzip-6-small-mem" => "gzip (level 6, small_mem)",
+            "gzip-9-small-mem" => "gzip (level 9, small_mem)",
+            "zlib-9" => "zlib (level 9)",
+            "rare" => "rare (level 9)",
+            "vpk0" => "vpk0 (two-sample)",
+            "vpk0-one-sample" => "vpk0 (one-sample)",
+            "lzkn64" => "lzkn64",
+            "lz10" => "lz77 (lz10)",
+            "lz11" => "lz77 (lz11)",
+            "lz10-vram-safe" => "lz77 (lz10, vram-safe)",
+            "lz11-vram-safe" => "lz77 (lz11, vram-safe)",
+            "rle" => "rle",
+            "huffman4" => "huffman (4-bit)",
+            "huffman8" => "huffman (8-bit)",
+            extension => panic!("Unknown test file extension {extension}"),
+        };
+
+        let candidates = super::identify(&bytes).unwrap();
+        assert!(candidates[0].is_match());
+        assert!(candidates
+            .iter()
+            .take_while(|candidate| candidate.is_match())
+            .any(|candidate| candidate.encoder.to_string() == expected));
+    }
+
+    #[test]
+    fn test_identify_closest() {
+        // Valid Yaz0 data storing "aaaaaaaa" as literals, which no encoder does
+        let mut bytes = b"Yaz0\0\0\0\x08".to_vec();
+        bytes.extend([0; 8]);
+        bytes.push(0xFF);
+        bytes.extend(b"aaaaaaaa");
+
+        let candidates = super::identify(&bytes).unwrap();
+        assert!(candidates.iter().all(|candidate| !candidate.is_match()));
+        assert_eq!(candidates[0].encoder.format(), crate::Format::Yaz0);
+        assert_eq!(candidates[0].first_difference, Some(0x10));
+    }
+}
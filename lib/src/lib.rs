@@ -4,12 +4,28 @@
 extern crate alloc;
 
 pub mod gzip;
+pub mod hal_lz;
+pub mod huffman;
+pub mod lz77;
+pub mod lzkn64;
 pub mod mio0;
+pub mod rare;
+pub mod rle;
+pub mod scan;
+#[cfg(feature = "std")]
+pub mod stream;
+pub mod vpk0;
 pub mod yay0;
 pub mod yaz0;
+pub mod zlib;
 
+mod identify;
 mod utils;
 
+pub use identify::{identify, Candidate, Encoder};
+
+use alloc::boxed::Box;
+use core::{fmt, str::FromStr};
 use thiserror::Error;
 
 #[cfg(feature = "python_bindings")]
@@ -41,6 +57,411 @@ pub enum Crunch64Error {
     NullPointer,
     #[error("Invalid compression level")]
     InvalidCompressionLevel,
+    #[error("File does not begin with gzip header")]
+    InvalidGzipHeader,
+    #[error("Invalid DEFLATE stream")]
+    InvalidDeflateStream,
+    #[error("Checksum does not match decompressed data")]
+    ChecksumMismatch,
+    #[error("File does not begin with zlib header")]
+    InvalidZlibHeader,
+    #[error("File does not begin with Rare header")]
+    InvalidRareHeader,
+    #[error("Compressed data ended unexpectedly")]
+    TruncatedInput,
+    #[error("Back-reference points before the start of the decompressed data")]
+    InvalidBackReference,
+    #[error("Decompressed data is larger than the size in the header")]
+    OutputOverrun,
+    #[error("File does not begin with a valid vpk0 header")]
+    InvalidVpk0Header,
+    #[error("File does not begin with a valid LZKN64 header")]
+    InvalidLzkn64Header,
+    #[error("Compressed data contains an invalid command")]
+    InvalidCommand,
+    #[error("File does not begin with a valid LZ77 header")]
+    InvalidLz77Header,
+    #[error("File does not begin with a valid RLE header")]
+    InvalidRleHeader,
+    #[error("File does not begin with a valid Huffman header")]
+    InvalidHuffmanHeader,
+    #[error("Decompressed size does not match the size in the header")]
+    SizeMismatch,
+}
+
+/// Common interface implemented by every compression format
+pub trait Codec {
+    /// Compresses `bytes`
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error>;
+
+    /// Decompresses `bytes`
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error>;
+
+    /// Decompresses `bytes` into the start of `dst` and returns the
+    /// decompressed size. Fails with [`Crunch64Error::OutOfBounds`] if `dst`
+    /// is too small.
+    ///
+    /// The codecs in this crate decompress directly into `dst`, while the
+    /// default implementation goes through [`Codec::decompress`].
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        let decompressed = self.decompress(bytes)?;
+        dst.get_mut(..decompressed.len())
+            .ok_or(Crunch64Error::OutOfBounds)?
+            .copy_from_slice(&decompressed);
+        Ok(decompressed.len())
+    }
+
+    /// Decompresses the compressed data at the start of `bytes` and returns it
+    /// together with the size of the compressed data. Anything after it is
+    /// ignored, which is useful when the data is part of a larger file.
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error>;
+
+    /// Returns a size big enough to hold the result of compressing `input_size` bytes
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error>;
+
+    /// Returns a size big enough to hold the result of decompressing `bytes`.
+    ///
+    /// Formats that don't store the uncompressed size have to decompress the
+    /// whole data to know it.
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error>;
+
+    /// Checks the header at the start of `bytes` and returns its size.
+    ///
+    /// Formats without a header (or with an optional one that is missing)
+    /// return 0.
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error>;
+}
+
+/// All the compression formats supported by this library
+///
+/// Every format can be used as a [`Codec`] with its default options.
+///
+/// The order of the variants must match [`Format::ALL`].
+/* This needs to be in sync with the C equivalent at `crunch64/format.h` */
+#[cfg_attr(feature = "c_bindings", repr(u32))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    Yay0,
+    Yaz0,
+    Mio0,
+    Gzip,
+    Zlib,
+    Deflate,
+    Rare,
+    Vpk0,
+    Lzkn64,
+    Lz77,
+    Rle,
+    Huffman,
+}
+
+impl Format {
+    pub const ALL: [Format; 12] = [
+        Format::Yay0,
+        Format::Yaz0,
+        Format::Mio0,
+        Format::Gzip,
+        Format::Zlib,
+        Format::Deflate,
+        Format::Rare,
+        Format::Vpk0,
+        Format::Lzkn64,
+        Format::Lz77,
+        Format::Rle,
+        Format::Huffman,
+    ];
+
+    /// Lowercase name of the format, as accepted by [`Format::from_str`]
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Yay0 => "yay0",
+            Format::Yaz0 => "yaz0",
+            Format::Mio0 => "mio0",
+            Format::Gzip => "gzip",
+            Format::Zlib => "zlib",
+            Format::Deflate => "deflate",
+            Format::Rare => "rare",
+            Format::Vpk0 => "vpk0",
+            Format::Lzkn64 => "lzkn64",
+            Format::Lz77 => "lz77",
+            Format::Rle => "rle",
+            Format::Huffman => "huffman",
+        }
+    }
+
+    /// Returns the [`Codec`] for this format with its default options
+    pub fn codec(self) -> &'static dyn Codec {
+        match self {
+            Format::Yay0 => &yay0::Yay0::DEFAULT,
+            Format::Yaz0 => &yaz0::Yaz0::DEFAULT,
+            Format::Mio0 => &mio0::Mio0::DEFAULT,
+            Format::Gzip => &gzip::Gzip::DEFAULT,
+            Format::Zlib => &zlib::Zlib::DEFAULT,
+            Format::Deflate => &gzip::Deflate::DEFAULT,
+            Format::Rare => &rare::Rare::DEFAULT,
+            Format::Vpk0 => &vpk0::Vpk0::DEFAULT,
+            Format::Lzkn64 => &lzkn64::Lzkn64::DEFAULT,
+            Format::Lz77 => &lz77::Lz77::DEFAULT,
+            Format::Rle => &rle::Rle::DEFAULT,
+            Format::Huffman => &huffman::Huffman::DEFAULT,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = Crunch64Error;
+
+    /// Parses a format name, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or(Crunch64Error::UnsupportedCompressionType)
+    }
+}
+
+impl TryFrom<u32> for Format {
+    type Error = Crunch64Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Format::ALL
+            .get(value as usize)
+            .copied()
+            .ok_or(Crunch64Error::UnsupportedCompressionType)
+    }
+}
+
+impl Codec for Format {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        self.codec().compress(bytes)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        self.codec().decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        self.codec().decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        self.codec().decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        self.codec().compress_bound(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        self.codec().decompress_bound(bytes)
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        self.codec().header_size(bytes)
+    }
+}
+
+/// Guesses the format of `bytes`.
+///
+/// Formats are recognized by their header. zlib and Rare headers are only 2
+/// bytes long and common in other data, so those formats are only accepted if
+/// the data can be fully decompressed, like gzip or raw DEFLATE without a
+/// header. Detecting them is slower.
+pub fn detect(bytes: &[u8]) -> Option<Format> {
+    for format in [Format::Yay0, Format::Yaz0, Format::Mio0, Format::Vpk0] {
+        if format.header_size(bytes).is_ok() {
+            return Some(format);
+        }
+    }
+
+    // gzip accepts data without a header too, so check the magic explicitly
+    if bytes.starts_with(&[0x1F, 0x8B]) && Format::Gzip.header_size(bytes).is_ok() {
+        return Some(Format::Gzip);
+    }
+
+    for format in [Format::Zlib, Format::Rare] {
+        if format.header_size(bytes).is_ok() && format.decompress_with_size(bytes).is_ok() {
+            return Some(format);
+        }
+    }
+
+    gzip::detect_headerless(bytes)
+}
+
+#[cfg(feature = "c_bindings")]
+mod c_bindings {
+    use super::{utils, Codec, Crunch64Error, Format};
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_decompress_bound(
+        format: u32,
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return Crunch64Error::NullPointer;
+        }
+
+        let format = match Format::try_from(format) {
+            Err(e) => return e,
+            Ok(f) => f,
+        };
+
+        let bytes = match utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match format.decompress_bound(bytes) {
+            Err(e) => return e,
+            Ok(value) => unsafe { *dst_size = value },
+        }
+
+        Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_decompress(
+        format: u32,
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return Crunch64Error::NullPointer;
+        }
+
+        let format = match Format::try_from(format) {
+            Err(e) => return e,
+            Ok(f) => f,
+        };
+
+        let bytes = match utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match format.decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_compress_bound(
+        format: u32,
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return Crunch64Error::NullPointer;
+        }
+
+        let format = match Format::try_from(format) {
+            Err(e) => return e,
+            Ok(f) => f,
+        };
+
+        match format.compress_bound(src_len) {
+            Err(e) => return e,
+            Ok(value) => unsafe { *dst_size = value },
+        }
+
+        Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_compress(
+        format: u32,
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return Crunch64Error::NullPointer;
+        }
+
+        let format = match Format::try_from(format) {
+            Err(e) => return e,
+            Ok(f) => f,
+        };
+
+        let bytes = match utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let data = match format.compress(bytes) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        Crunch64Error::Okay
+    }
+}
+
+#[cfg(feature = "python_bindings")]
+mod python_bindings {
+    use super::{Codec, Crunch64Error, Format};
+    use pyo3::prelude::*;
+    use std::borrow::Cow;
+
+    /**
+     * Formats are passed as their names (e.g. `"yaz0"`), see `Format::name`.
+     */
+
+    #[pyfunction]
+    pub(crate) fn decompress<'a>(
+        format: &str,
+        bytes: Cow<'a, [u8]>,
+    ) -> Result<Cow<'a, [u8]>, Crunch64Error> {
+        Ok(Cow::Owned(
+            format.parse::<Format>()?.decompress(&bytes)?.into(),
+        ))
+    }
+
+    #[pyfunction]
+    pub(crate) fn detect(bytes: Cow<[u8]>) -> Option<&'static str> {
+        super::detect(&bytes).map(Format::name)
+    }
+
+    #[pyfunction]
+    pub(crate) fn compress<'a>(
+        format: &str,
+        bytes: Cow<'a, [u8]>,
+    ) -> Result<Cow<'a, [u8]>, Crunch64Error> {
+        Ok(Cow::Owned(
+            format.parse::<Format>()?.compress(&bytes)?.into(),
+        ))
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Crunch64Error> for std::io::Error {
+    fn from(err: Crunch64Error) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
 }
 
 #[cfg(feature = "python_bindings")]
@@ -53,12 +474,297 @@ impl std::convert::From<Crunch64Error> for PyErr {
 #[cfg(feature = "python_bindings")]
 #[pymodule]
 fn crunch64(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(python_bindings::decompress, m)?)?;
+    m.add_function(wrap_pyfunction!(python_bindings::compress, m)?)?;
+    m.add_function(wrap_pyfunction!(python_bindings::detect, m)?)?;
     m.add_function(wrap_pyfunction!(yay0::python_bindings::decompress_yay0, m)?)?;
     m.add_function(wrap_pyfunction!(yay0::python_bindings::compress_yay0, m)?)?;
     m.add_function(wrap_pyfunction!(yaz0::python_bindings::decompress_yaz0, m)?)?;
     m.add_function(wrap_pyfunction!(yaz0::python_bindings::compress_yaz0, m)?)?;
     m.add_function(wrap_pyfunction!(mio0::python_bindings::decompress_mio0, m)?)?;
     m.add_function(wrap_pyfunction!(mio0::python_bindings::compress_mio0, m)?)?;
+    m.add_function(wrap_pyfunction!(gzip::python_bindings::decompress_gzip, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        gzip::python_bindings::decompress_gzip_raw,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(
+        gzip::python_bindings::compress_gzip_raw,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(zlib::python_bindings::decompress_zlib, m)?)?;
+    m.add_function(wrap_pyfunction!(rare::python_bindings::decompress_rare, m)?)?;
+    m.add_function(wrap_pyfunction!(rare::python_bindings::compress_rare, m)?)?;
+    m.add_function(wrap_pyfunction!(zlib::python_bindings::compress_zlib, m)?)?;
+    m.add_function(wrap_pyfunction!(vpk0::python_bindings::decompress_vpk0, m)?)?;
+    m.add_function(wrap_pyfunction!(vpk0::python_bindings::compress_vpk0, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        lzkn64::python_bindings::decompress_lzkn64,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(
+        lzkn64::python_bindings::compress_lzkn64,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(lz77::python_bindings::decompress_lz77, m)?)?;
+    m.add_function(wrap_pyfunction!(lz77::python_bindings::compress_lz77, m)?)?;
+    m.add_function(wrap_pyfunction!(rle::python_bindings::decompress_rle, m)?)?;
+    m.add_function(wrap_pyfunction!(rle::python_bindings::compress_rle, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        huffman::python_bindings::decompress_huffman,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(
+        huffman::python_bindings::compress_huffman,
+        m
+    )?)?;
     m.add_function(wrap_pyfunction!(gzip::python_bindings::compress_gzip, m)?)?;
     Ok(())
 }
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use super::{mio0, yay0, yaz0, Codec, Crunch64Error, Format};
+    use rstest::rstest;
+    use std::{fs, io::Read, path::PathBuf};
+
+    #[test]
+    fn test_format_names() {
+        for (i, format) in Format::ALL.into_iter().enumerate() {
+            assert_eq!(format.name().parse::<Format>(), Ok(format));
+            assert_eq!(format.name().to_uppercase().parse::<Format>(), Ok(format));
+            assert_eq!(Format::try_from(i as u32), Ok(format));
+            assert_eq!(format as u32, i as u32);
+        }
+
+        assert_eq!(
+            "lzss".parse::<Format>(),
+            Err(Crunch64Error::UnsupportedCompressionType)
+        );
+        assert_eq!(
+            Format::try_from(Format::ALL.len() as u32),
+            Err(Crunch64Error::UnsupportedCompressionType)
+        );
+    }
+
+    #[test]
+    fn test_codec_cycle() -> Result<(), Crunch64Error> {
+        let decompressed_file = fs::read(PathBuf::from("../test_data/small.txt")).unwrap();
+
+        for format in Format::ALL {
+            let compressed = format.compress(&decompressed_file)?;
+            assert!(compressed.len() <= format.compress_bound(decompressed_file.len())?);
+            assert!(format.decompress_bound(&compressed)? >= decompressed_file.len());
+            assert!(format.header_size(&compressed)? <= compressed.len());
+
+            let decompressed = format.decompress(&compressed)?;
+            assert_eq!(decompressed_file, decompressed.as_ref(), "{}", format);
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_compress_bound(
+        #[values(
+            Format::Yay0,
+            Format::Yaz0,
+            Format::Mio0,
+            Format::Gzip,
+            Format::Zlib,
+            Format::Deflate,
+            Format::Rare,
+            Format::Vpk0,
+            Format::Lzkn64,
+            Format::Lz77,
+            Format::Rle,
+            Format::Huffman
+        )]
+        format: Format,
+    ) -> Result<(), Crunch64Error> {
+        // Pseudo-random bytes, which no format can compress
+        let mut state = 1u32;
+        let data: Vec<u8> = (0..0x2001)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+
+        for len in (0..0x40).chain([0x1000, 0x1001, 0x2001]) {
+            let compressed = format.compress(&data[..len])?;
+            assert!(
+                compressed.len() <= format.compress_bound(len)?,
+                "0x{:X} bytes",
+                len
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompress_into() -> Result<(), Crunch64Error> {
+        let decompressed_file = fs::read(PathBuf::from("../test_data/small.txt")).unwrap();
+
+        for format in Format::ALL {
+            let compressed = format.compress(&decompressed_file)?;
+
+            let mut dst = vec![0xAA; decompressed_file.len() + 0x10];
+            let size = format.decompress_into(&compressed, &mut dst)?;
+            assert_eq!(size, decompressed_file.len(), "{}", format);
+            assert_eq!(&dst[..size], decompressed_file, "{}", format);
+            assert!(dst[size..].iter().all(|&b| b == 0xAA), "{}", format);
+
+            assert_eq!(
+                format.decompress_into(&compressed, &mut dst[..decompressed_file.len() - 1]),
+                Err(Crunch64Error::OutOfBounds),
+                "{}",
+                format
+            );
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_decompress_with_size(
+        #[values("small.txt", "x86-64_rabbitizer.bin")] name: &str,
+    ) -> Result<(), Crunch64Error> {
+        let decompressed_file = fs::read(PathBuf::from("../test_data").join(name)).unwrap();
+
+        for format in Format::ALL {
+            let compressed = format.compress(&decompressed_file)?;
+
+            // The compressed data is followed by something else, like in a ROM
+            let mut bytes = compressed.to_vec();
+            bytes.extend([0xAA; 0x20]);
+
+            let (decompressed, size) = format.decompress_with_size(&bytes)?;
+            assert_eq!(size, compressed.len(), "{}", format);
+            assert_eq!(decompressed.as_ref(), decompressed_file, "{}", format);
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(".Yay0", Format::Yay0)]
+    #[case(".Yaz0", Format::Yaz0)]
+    #[case(".MIO0", Format::Mio0)]
+    #[case(".gzip-1", Format::Gzip)]
+    #[case(".gzip-9", Format::Gzip)]
+    #[case(".gz", Format::Gzip)]
+    #[case(".zlib-9", Format::Zlib)]
+    #[case(".rare", Format::Rare)]
+    #[case(".vpk0", Format::Vpk0)]
+    fn test_detect(#[case] extension: &str, #[case] format: Format) {
+        let mut found = false;
+        for entry in fs::read_dir("../test_data").unwrap() {
+            let path = entry.unwrap().path();
+            if !path.to_string_lossy().ends_with(extension) {
+                continue;
+            }
+            found = true;
+
+            let compressed_file = fs::read(&path).unwrap();
+            assert_eq!(super::detect(&compressed_file), Some(format), "{:?}", path);
+        }
+        assert!(found);
+    }
+
+    #[test]
+    fn test_detect_deflate() {
+        let compressed_file = fs::read("../test_data/small.txt.gzip-9").unwrap();
+        let stream = &compressed_file[..compressed_file.len() - 8];
+        assert_eq!(super::detect(stream), Some(Format::Deflate));
+
+        let mut padded = stream.to_vec();
+        padded.resize(padded.len().next_multiple_of(0x10), 0);
+        assert_eq!(super::detect(&padded), Some(Format::Deflate));
+    }
+
+    #[rstest]
+    #[case::zlib(&[0x78, 0x9C])]
+    #[case::rare(&[0x11, 0x72, 0x00, 0x00, 0x01, 0x00])]
+    fn test_detect_header_only(#[case] header: &[u8]) {
+        let mut bytes = header.to_vec();
+        bytes.extend(fs::read("../test_data/small.txt").unwrap());
+        assert_eq!(super::detect(&bytes), None);
+    }
+
+    #[rstest]
+    fn test_detect_uncompressed(
+        #[values(
+            "small.txt",
+            "mips_gist_wiseguy_yaz0.bin",
+            "x86-64_rabbitizer.bin",
+            "dirt.png"
+        )]
+        name: &str,
+    ) {
+        let file = fs::read(PathBuf::from("../test_data").join(name)).unwrap();
+        assert_eq!(super::detect(&file), None);
+    }
+
+    #[rstest]
+    #[case(Format::Yay0, "small.txt.Yay0")]
+    #[case(Format::Yaz0, "small.txt.Yaz0")]
+    #[case(Format::Mio0, "small.txt.MIO0")]
+    #[case(Format::Gzip, "small.txt.gz")]
+    #[case(Format::Zlib, "small.txt.zlib-9")]
+    #[case(Format::Rare, "small.txt.rare")]
+    #[case(Format::Vpk0, "small.txt.vpk0")]
+    #[case(Format::Lzkn64, "small.txt.lzkn64")]
+    #[case(Format::Lz77, "small.txt.lz10")]
+    #[case(Format::Lz77, "small.txt.lz11")]
+    #[case(Format::Rle, "small.txt.rle")]
+    #[case(Format::Huffman, "small.txt.huffman4")]
+    #[case(Format::Huffman, "small.txt.huffman8")]
+    fn test_corrupt_input(#[case] format: Format, #[case] name: &str) {
+        let compressed_file = fs::read(PathBuf::from("../test_data").join(name)).unwrap();
+
+        // None of these may panic, but they don't need to fail either
+        for len in 0..compressed_file.len() {
+            let _ = format.decompress(&compressed_file[..len]);
+            let _ = format.decompress_bound(&compressed_file[..len]);
+            check_stream_decompress(format, &compressed_file[..len]);
+        }
+
+        for i in 0..compressed_file.len() {
+            for mask in [0x01, 0x10, 0x80, 0xFF] {
+                let mut corrupted = compressed_file.clone();
+                corrupted[i] ^= mask;
+                let _ = super::detect(&corrupted);
+                check_stream_decompress(format, &corrupted);
+
+                if let Ok(decompressed) = format.decompress(&corrupted) {
+                    let mut dst = vec![0; decompressed.len()];
+                    assert_eq!(
+                        format.decompress_into(&corrupted, &mut dst),
+                        Ok(decompressed.len())
+                    );
+                    assert_eq!(dst, decompressed.as_ref());
+                }
+            }
+        }
+    }
+
+    // Whenever a streaming decoder succeeds, it must agree with `decompress`
+    fn check_stream_decompress(format: Format, bytes: &[u8]) {
+        let mut streamed = Vec::new();
+        let result = match format {
+            Format::Yay0 => {
+                yay0::Yay0Decoder::new(bytes).and_then(|mut d| d.read_to_end(&mut streamed))
+            }
+            Format::Yaz0 => {
+                yaz0::Yaz0Decoder::new(bytes).and_then(|mut d| d.read_to_end(&mut streamed))
+            }
+            Format::Mio0 => {
+                mio0::Mio0Decoder::new(bytes).and_then(|mut d| d.read_to_end(&mut streamed))
+            }
+            _ => return,
+        };
+
+        if result.is_ok() {
+            assert_eq!(format.decompress(bytes).as_deref(), Ok(streamed.as_slice()));
+        }
+    }
+}
@@ -0,0 +1,619 @@
+// Implements the LZ77 compression of the GBA/DS BIOS and Nintendo's later
+// toolchains, in its LZ10 and LZ11 variants. After a little-endian header with
+// the variant and the uncompressed size, each flag byte tells whether the next
+// 8 items are literals (0 bits) or back-references (1 bits), from the most
+// significant bit down, like Yaz0 with the meaning of the bits swapped.
+//
+// Back-references are stored as the distance minus 1 in 12 bits and the
+// length in the remaining bits. LZ10 always uses 2 bytes, while LZ11 uses the
+// top 4 bits of the first byte to pick between 2, 3 and 4 byte encodings for
+// longer matches.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    utils::{self, Group},
+    Codec, Crunch64Error,
+};
+
+const MAX_LZ10_LENGTH: usize = 0x12;
+const MAX_LZ11_LENGTH: usize = 0x10110;
+
+/// Variant of the format, stored in the first byte of the header
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Variant {
+    /// Type 0x10, with back-references up to 0x12 bytes long. This is the
+    /// variant supported by the GBA BIOS.
+    #[default]
+    Lz10,
+    /// Type 0x11, with back-references up to 0x10110 bytes long
+    Lz11,
+}
+
+impl Variant {
+    /// Lowercase name of the variant
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Lz10 => "lz10",
+            Variant::Lz11 => "lz11",
+        }
+    }
+}
+
+/// Fields of the LZ77 header, 4 bytes long or 8 bytes if the size doesn't fit
+/// in 24 bits
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Lz77Header {
+    pub variant: Variant,
+    pub uncompressed_size: usize,
+}
+
+impl Lz77Header {
+    pub fn parse(bytes: &[u8]) -> Result<Lz77Header, Crunch64Error> {
+        if bytes.len() < 4 {
+            return Err(Crunch64Error::InvalidLz77Header);
+        }
+
+        let variant = match bytes[0] {
+            0x10 => Variant::Lz10,
+            0x11 => Variant::Lz11,
+            _ => return Err(Crunch64Error::InvalidLz77Header),
+        };
+
+        let (uncompressed_size, _) =
+            utils::parse_bios_header_size(bytes).ok_or(Crunch64Error::InvalidLz77Header)?;
+
+        Ok(Lz77Header {
+            variant,
+            uncompressed_size,
+        })
+    }
+
+    /// Size of the header in bytes
+    pub fn size(&self) -> usize {
+        utils::bios_header_size(self.uncompressed_size)
+    }
+
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        let kind = match self.variant {
+            Variant::Lz10 => 0x10,
+            Variant::Lz11 => 0x11,
+        };
+        utils::write_bios_header(dst, kind, self.uncompressed_size);
+    }
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data,
+/// which can be followed by unrelated data in `bytes`
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let header = Lz77Header::parse(bytes)?;
+
+    let mut ret = utils::decompression_buffer(header.uncompressed_size, bytes.len());
+    let compressed_size = decompress_to(bytes, &header, &mut ret)?;
+
+    Ok((ret.into_boxed_slice(), compressed_size))
+}
+
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let header = Lz77Header::parse(bytes)?;
+
+    let dst = dst
+        .get_mut(..header.uncompressed_size)
+        .ok_or(Crunch64Error::OutOfBounds)?;
+    decompress_to(bytes, &header, &mut utils::SliceOutput::new(dst))?;
+
+    Ok(header.uncompressed_size)
+}
+
+fn decompress_to<O: utils::Output>(
+    bytes: &[u8],
+    header: &Lz77Header,
+    ret: &mut O,
+) -> Result<usize, Crunch64Error> {
+    let decompressed_size = header.uncompressed_size;
+    let mut input_pos = header.size();
+
+    while ret.len() < decompressed_size {
+        let flags = utils::next_u8(bytes, &mut input_pos)?;
+
+        for bit in (0..8).rev() {
+            if ret.len() >= decompressed_size {
+                break;
+            }
+
+            if flags & (1 << bit) == 0 {
+                ret.push(utils::next_u8(bytes, &mut input_pos)?)?;
+                continue;
+            }
+
+            let first = utils::next_u8(bytes, &mut input_pos)? as usize;
+            let length = match header.variant {
+                Variant::Lz10 => (first >> 4) + 3,
+                Variant::Lz11 => match first >> 4 {
+                    0 => {
+                        let second = utils::next_u8(bytes, &mut input_pos)? as usize;
+                        ((first & 0xF) << 4 | second >> 4) + 0x11
+                    }
+                    1 => {
+                        let second = utils::next_u8(bytes, &mut input_pos)? as usize;
+                        let third = utils::next_u8(bytes, &mut input_pos)? as usize;
+                        ((first & 0xF) << 12 | second << 4 | third >> 4) + 0x111
+                    }
+                    indicator => indicator + 1,
+                },
+            };
+
+            // The last byte holds the low bits of the distance, and the byte
+            // before it the top 4 bits
+            let previous = bytes[input_pos - 1] as usize;
+            let next = utils::next_u8(bytes, &mut input_pos)? as usize;
+            let distance = ((previous & 0xF) << 8 | next) + 1;
+
+            utils::copy_back_reference(ret, distance, length, decompressed_size)?;
+        }
+    }
+
+    Ok(input_pos)
+}
+
+fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
+    // Every byte as a literal, plus a flag byte for every 8 of them
+    Ok(8 + input_size + input_size.div_ceil(8))
+}
+
+/// Options for [`compress_with_options`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CompressOptions {
+    pub variant: Variant,
+    /// Don't copy from the byte just written, so that the data can be
+    /// decompressed straight into VRAM, which is written 16 bits at a time
+    pub vram_safe: bool,
+}
+
+impl CompressOptions {
+    pub const DEFAULT: CompressOptions = CompressOptions {
+        variant: Variant::Lz10,
+        vram_safe: false,
+    };
+}
+
+impl Default for CompressOptions {
+    fn default() -> CompressOptions {
+        CompressOptions::DEFAULT
+    }
+}
+
+pub fn compress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    compress_with_options(bytes, &CompressOptions::DEFAULT)
+}
+
+fn write_back_reference(output: &mut Vec<u8>, variant: Variant, offset: usize, length: usize) {
+    match variant {
+        Variant::Lz10 => output.push(((length - 3) << 4 | offset >> 8) as u8),
+        Variant::Lz11 if length <= 0x10 => output.push(((length - 1) << 4 | offset >> 8) as u8),
+        Variant::Lz11 if length <= 0x110 => {
+            let length = length - 0x11;
+            output.extend([
+                (length >> 4) as u8,
+                ((length & 0xF) << 4 | offset >> 8) as u8,
+            ]);
+        }
+        Variant::Lz11 => {
+            let length = length - 0x111;
+            output.extend([
+                (0x10 | length >> 12) as u8,
+                (length >> 4) as u8,
+                ((length & 0xF) << 4 | offset >> 8) as u8,
+            ]);
+        }
+    }
+    output.push(offset as u8);
+}
+
+/// Compresses `bytes` with the same greedy parser as Yaz0. The output isn't
+/// known to match Nintendo's encoder.
+pub fn compress_with_options(
+    bytes: &[u8],
+    options: &CompressOptions,
+) -> Result<Box<[u8]>, Crunch64Error> {
+    let max_match_length = match options.variant {
+        Variant::Lz10 => MAX_LZ10_LENGTH,
+        Variant::Lz11 => MAX_LZ11_LENGTH,
+    };
+    let min_distance = if options.vram_safe { 2 } else { 1 };
+    let groups = utils::parse_greedy(bytes, max_match_length, min_distance);
+
+    let mut output = Vec::with_capacity(size_for_compressed_buffer(bytes.len())?);
+    Lz77Header {
+        variant: options.variant,
+        uncompressed_size: bytes.len(),
+    }
+    .write(&mut output);
+
+    let mut input_pos = 0;
+    for chunk in groups.chunks(8) {
+        let flags_pos = output.len();
+        output.push(0);
+
+        for (i, group) in chunk.iter().enumerate() {
+            match *group {
+                Group::Literal => {
+                    output.push(bytes[input_pos]);
+                    input_pos += 1;
+                }
+                Group::BackReference { offset, length } => {
+                    output[flags_pos] |= 0x80 >> i;
+                    write_back_reference(
+                        &mut output,
+                        options.variant,
+                        offset as usize,
+                        length as usize,
+                    );
+                    input_pos += length as usize;
+                }
+            }
+        }
+    }
+
+    Ok(output.into_boxed_slice())
+}
+
+/// LZ77 [`Codec`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Lz77 {
+    pub options: CompressOptions,
+}
+
+impl Lz77 {
+    pub const DEFAULT: Lz77 = Lz77 {
+        options: CompressOptions::DEFAULT,
+    };
+}
+
+impl Codec for Lz77 {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress_with_options(bytes, &self.options)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Ok(Lz77Header::parse(bytes)?.uncompressed_size)
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Ok(Lz77Header::parse(bytes)?.size())
+    }
+}
+
+#[cfg(feature = "c_bindings")]
+mod c_bindings {
+    #[no_mangle]
+    pub extern "C" fn crunch64_lz77_decompress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::Lz77Header::parse(bytes) {
+            Err(e) => return e,
+            Ok(header) => unsafe { *dst_size = header.uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_lz77_decompress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_lz77_compress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        match super::size_for_compressed_buffer(src_len) {
+            Err(e) => return e,
+            Ok(uncompressed_size) => unsafe { *dst_size = uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_lz77_compress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+        lz11: bool,
+        vram_safe: bool,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let options = super::CompressOptions {
+            variant: if lz11 {
+                super::Variant::Lz11
+            } else {
+                super::Variant::Lz10
+            },
+            vram_safe,
+        };
+        let data = match super::compress_with_options(bytes, &options) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+}
+
+#[cfg(feature = "python_bindings")]
+pub(crate) mod python_bindings {
+    use pyo3::prelude::*;
+    use std::borrow::Cow;
+
+    /**
+     * We use a `Cow` instead of a plain &[u8] because the latter only allows Python's
+     * `bytes` objects, while `Cow`` allows for both `bytes` and `bytearray`.
+     * This is important because an argument typed as `bytes` allows to pass a
+     * `bytearray` object too.
+     */
+
+    #[pyfunction]
+    pub(crate) fn decompress_lz77(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::decompress(&bytes)?.into()))
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (bytes, lz11=false, vram_safe=false))]
+    pub(crate) fn compress_lz77(
+        bytes: Cow<[u8]>,
+        lz11: bool,
+        vram_safe: bool,
+    ) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        let options = super::CompressOptions {
+            variant: if lz11 {
+                super::Variant::Lz11
+            } else {
+                super::Variant::Lz10
+            },
+            vram_safe,
+        };
+        Ok(Cow::Owned(
+            super::compress_with_options(&bytes, &options)?.into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use super::{CompressOptions, Lz77Header, Variant};
+    use crate::Crunch64Error;
+    use rstest::rstest;
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    fn options_for(path: &Path) -> CompressOptions {
+        let extension = path.extension().unwrap().to_str().unwrap();
+        CompressOptions {
+            variant: if extension.starts_with("lz11") {
+                Variant::Lz11
+            } else {
+                Variant::Lz10
+            },
+            vram_safe: extension.ends_with("-vram-safe"),
+        }
+    }
+
+    // The test files were made by this encoder, not taken from real data
+    #[rstest]
+    fn test_decompression_regression(
+        #[files("../test_data/*.lz10*")]
+        #[files("../test_data/*.lz11*")]
+        path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let decompressed = super::decompress(&compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_compression_regression(
+        #[files("../test_data/*.lz10*")]
+        #[files("../test_data/*.lz11*")]
+        path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let compressed = super::compress_with_options(&decompressed_file, &options_for(&path))?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_vram_safe(
+        #[files("../test_data/*.lz10-vram-safe")]
+        #[files("../test_data/*.lz11-vram-safe")]
+        path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let header = Lz77Header::parse(&compressed_file)?;
+
+        // Walk the back-references and check that none copies the previous byte
+        let mut input_pos = header.size();
+        let mut output_size = 0;
+        while output_size < header.uncompressed_size {
+            let flags = compressed_file[input_pos];
+            input_pos += 1;
+            for bit in (0..8).rev() {
+                if output_size >= header.uncompressed_size {
+                    break;
+                }
+                if flags & (1 << bit) == 0 {
+                    input_pos += 1;
+                    output_size += 1;
+                    continue;
+                }
+
+                let first = compressed_file[input_pos] as usize;
+                let (size, length) = match (header.variant, first >> 4) {
+                    (Variant::Lz10, indicator) => (2, indicator + 3),
+                    (Variant::Lz11, 0) => {
+                        let second = compressed_file[input_pos + 1] as usize;
+                        (3, ((first & 0xF) << 4 | second >> 4) + 0x11)
+                    }
+                    (Variant::Lz11, 1) => {
+                        let second = compressed_file[input_pos + 1] as usize;
+                        let third = compressed_file[input_pos + 2] as usize;
+                        (4, ((first & 0xF) << 12 | second << 4 | third >> 4) + 0x111)
+                    }
+                    (Variant::Lz11, indicator) => (2, indicator + 1),
+                };
+                let offset = ((compressed_file[input_pos + size - 2] as usize & 0xF) << 8)
+                    | compressed_file[input_pos + size - 1] as usize;
+                assert_ne!(offset, 0, "{:?} at 0x{:X}", path, input_pos);
+
+                input_pos += size;
+                output_size += length;
+            }
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_header_cycle(
+        #[values(Variant::Lz10, Variant::Lz11)] variant: Variant,
+        #[values(0, 1, 0xFFFFFF, 0x1000000)] uncompressed_size: usize,
+    ) -> Result<(), Crunch64Error> {
+        let header = Lz77Header {
+            variant,
+            uncompressed_size,
+        };
+
+        let mut bytes = Vec::new();
+        header.write(&mut bytes);
+        assert_eq!(bytes.len(), header.size());
+        assert_eq!(Lz77Header::parse(&bytes)?, header);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lz11_lengths() -> Result<(), Crunch64Error> {
+        // Runs long enough to need each of the back-reference encodings
+        let mut data = Vec::new();
+        for length in [0x10, 0x110, 0x8000, 0x10110] {
+            data.push(0xAA);
+            data.extend(vec![length as u8; length + 1]);
+        }
+
+        let options = CompressOptions {
+            variant: Variant::Lz11,
+            vram_safe: false,
+        };
+        let compressed = super::compress_with_options(&data, &options)?;
+        assert!(compressed.len() < 0x50);
+        assert_eq!(super::decompress(&compressed)?.as_ref(), data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_errors() {
+        assert_eq!(
+            super::decompress(&[0x12, 0x01, 0x00, 0x00, 0x00, 0x00]),
+            Err(Crunch64Error::InvalidLz77Header)
+        );
+
+        // Back-reference before the start of the data
+        assert_eq!(
+            super::decompress(&[0x10, 0x04, 0x00, 0x00, 0x40, b'a', 0x00, 0x01]),
+            Err(Crunch64Error::InvalidBackReference)
+        );
+
+        // Back-reference past the size in the header
+        assert_eq!(
+            super::decompress(&[0x10, 0x04, 0x00, 0x00, 0x40, b'a', 0x10, 0x00]),
+            Err(Crunch64Error::OutputOverrun)
+        );
+
+        assert_eq!(
+            super::decompress(&[0x11, 0x04, 0x00, 0x00, 0x00, b'a']),
+            Err(Crunch64Error::TruncatedInput)
+        );
+    }
+}
@@ -0,0 +1,462 @@
+// Implements the LZKN64 compression used by Konami's N64 games (e.g.
+// Goemon's Great Adventure, Castlevania 64 and Hybrid Heaven). The data starts
+// with the big-endian compressed size, including the 4-byte header, followed
+// by a sequence of commands:
+//
+// - 0x00-0x7F: back-reference. With the next byte, the low 10 bits are the
+//   distance and the top 5 bits the length minus 2.
+// - 0x80-0x9F: the low 5 bits are the number of literal bytes that follow.
+// - 0xC0-0xDF: the next byte repeated (low 5 bits + 2) times.
+// - 0xE0-0xFE: zero repeated (low 5 bits + 2) times.
+// - 0xFF: zero repeated (next byte + 2) times.
+//
+// The uncompressed size isn't stored, decompression stops at the end of the
+// compressed data.
+//
+// No data from these games has been tested, so the decoder is only known to
+// read this crate's output, and the encoder makes no attempt at Konami's.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    utils::{self, Output},
+    Codec, Crunch64Error,
+};
+
+const HEADER_SIZE: usize = 4;
+
+const WINDOW_SIZE: usize = 0x3DF;
+const MAX_COPY_LENGTH: usize = 0x21;
+const MAX_LITERALS: usize = 0x1F;
+const MAX_RLE_LENGTH: usize = 0x21;
+const MAX_ZERO_RLE_LENGTH: usize = 0x101;
+
+// Runs shorter than this are cheaper as back-references or literals
+const MIN_RLE_LENGTH: usize = 3;
+
+// Returns the compressed size stored in the header
+fn parse_header(bytes: &[u8]) -> Result<usize, Crunch64Error> {
+    let compressed_size =
+        utils::read_u32(bytes, 0).map_err(|_| Crunch64Error::InvalidLzkn64Header)? as usize;
+
+    if compressed_size < HEADER_SIZE {
+        return Err(Crunch64Error::InvalidLzkn64Header);
+    }
+
+    Ok(compressed_size)
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data from
+/// the header, which can be followed by unrelated data in `bytes`
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let compressed_size = parse_header(bytes)?;
+
+    let mut ret = Vec::new();
+    decompress_to(bytes, compressed_size, &mut ret)?;
+
+    Ok((ret.into_boxed_slice(), compressed_size))
+}
+
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let compressed_size = parse_header(bytes)?;
+
+    let mut output = utils::SliceOutput::new(dst);
+    decompress_to(bytes, compressed_size, &mut output)?;
+
+    Ok(output.len())
+}
+
+fn decompress_to<O: Output>(
+    bytes: &[u8],
+    compressed_size: usize,
+    ret: &mut O,
+) -> Result<(), Crunch64Error> {
+    let bytes = bytes
+        .get(..compressed_size)
+        .ok_or(Crunch64Error::TruncatedInput)?;
+    let mut input_pos = HEADER_SIZE;
+
+    while input_pos < bytes.len() {
+        let command = utils::next_u8(bytes, &mut input_pos)? as usize;
+
+        match command {
+            0x00..=0x7F => {
+                let next = utils::next_u8(bytes, &mut input_pos)? as usize;
+                let distance = ((command & 0x3) << 8) | next;
+                let length = (command >> 2) + 2;
+                utils::copy_back_reference(ret, distance, length, usize::MAX)?;
+            }
+            0x80..=0x9F => {
+                let length = command & 0x1F;
+                let literals = bytes
+                    .get(input_pos..input_pos + length)
+                    .ok_or(Crunch64Error::TruncatedInput)?;
+                ret.extend_from_slice(literals)?;
+                input_pos += length;
+            }
+            0xC0..=0xDF => {
+                let byte = utils::next_u8(bytes, &mut input_pos)?;
+                for _ in 0..(command & 0x1F) + 2 {
+                    ret.push(byte)?;
+                }
+            }
+            0xE0..=0xFE => {
+                for _ in 0..(command & 0x1F) + 2 {
+                    ret.push(0)?;
+                }
+            }
+            0xFF => {
+                let length = utils::next_u8(bytes, &mut input_pos)? as usize + 2;
+                for _ in 0..length {
+                    ret.push(0)?;
+                }
+            }
+            _ => return Err(Crunch64Error::InvalidCommand),
+        }
+    }
+
+    Ok(())
+}
+
+fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
+    // Literals take an extra byte for every 0x1F bytes
+    Ok(HEADER_SIZE + input_size + input_size.div_ceil(MAX_LITERALS))
+}
+
+// Writes the pending literals, if any
+fn flush_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        output.push(0x80 | chunk.len() as u8);
+        output.extend(chunk);
+    }
+}
+
+fn write_rle(output: &mut Vec<u8>, byte: u8, length: usize) {
+    if byte != 0 {
+        output.extend([0xC0 | (length - 2) as u8, byte]);
+    } else if length - 2 < 0x1F {
+        output.push(0xE0 | (length - 2) as u8);
+    } else {
+        output.extend([0xFF, (length - 2) as u8]);
+    }
+}
+
+/// Compresses `bytes`, using runs of repeated bytes where they are longer
+/// than the back-reference found in the window. The output isn't known to
+/// match Konami's encoder.
+pub fn compress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    let mut output = Vec::with_capacity(size_for_compressed_buffer(bytes.len())?);
+    output.extend([0; HEADER_SIZE]);
+
+    let mut window = utils::Window::new(bytes);
+    let mut literals_start = 0;
+    let mut input_pos = 0;
+
+    while input_pos < bytes.len() {
+        let byte = bytes[input_pos];
+        let max_rle_length = if byte == 0 {
+            MAX_ZERO_RLE_LENGTH
+        } else {
+            MAX_RLE_LENGTH
+        };
+        let rle_length = bytes[input_pos..]
+            .iter()
+            .take(max_rle_length)
+            .take_while(|&&b| b == byte)
+            .count();
+
+        let (match_pos, match_length) =
+            window.search_within(input_pos, MAX_COPY_LENGTH, 1..=WINDOW_SIZE);
+        let match_length = match_length as usize;
+
+        // Runs of zeros up to 0x20 bytes long take a single byte, so they win ties
+        let use_match = match_length > rle_length || (match_length == rle_length && byte != 0);
+
+        // If the match isn't larger than 2 bytes, copying the input without compression is smaller
+        if match_length > 2 && use_match {
+            flush_literals(&mut output, &bytes[literals_start..input_pos]);
+            let distance = input_pos - match_pos as usize;
+            output.extend([
+                (((match_length - 2) << 2) | (distance >> 8)) as u8,
+                distance as u8,
+            ]);
+            input_pos += match_length;
+            literals_start = input_pos;
+        } else if rle_length >= MIN_RLE_LENGTH {
+            flush_literals(&mut output, &bytes[literals_start..input_pos]);
+            write_rle(&mut output, byte, rle_length);
+            input_pos += rle_length;
+            literals_start = input_pos;
+        } else {
+            input_pos += 1;
+        }
+    }
+    flush_literals(&mut output, &bytes[literals_start..]);
+
+    let compressed_size = output.len() as u32;
+    output[..HEADER_SIZE].copy_from_slice(&compressed_size.to_be_bytes());
+
+    Ok(output.into_boxed_slice())
+}
+
+/// LZKN64 [`Codec`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Lzkn64;
+
+impl Lzkn64 {
+    pub const DEFAULT: Lzkn64 = Lzkn64;
+}
+
+impl Codec for Lzkn64 {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress(bytes)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        // LZKN64 doesn't store the uncompressed size, so the only way to know
+        // it is to decompress the data
+        Ok(decompress(bytes)?.len())
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        parse_header(bytes)?;
+        Ok(HEADER_SIZE)
+    }
+}
+
+#[cfg(feature = "c_bindings")]
+mod c_bindings {
+    #[no_mangle]
+    pub extern "C" fn crunch64_lzkn64_decompress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        // LZKN64 doesn't store the uncompressed size, so the only way to know
+        // it is to decompress the data
+        match super::decompress(bytes) {
+            Err(e) => return e,
+            Ok(data) => unsafe { *dst_size = data.len() },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_lzkn64_decompress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_lzkn64_compress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        match super::size_for_compressed_buffer(src_len) {
+            Err(e) => return e,
+            Ok(uncompressed_size) => unsafe { *dst_size = uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_lzkn64_compress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let data = match super::compress(bytes) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+}
+
+#[cfg(feature = "python_bindings")]
+pub(crate) mod python_bindings {
+    use pyo3::prelude::*;
+    use std::borrow::Cow;
+
+    /**
+     * We use a `Cow` instead of a plain &[u8] because the latter only allows Python's
+     * `bytes` objects, while `Cow`` allows for both `bytes` and `bytearray`.
+     * This is important because an argument typed as `bytes` allows to pass a
+     * `bytearray` object too.
+     */
+
+    #[pyfunction]
+    pub(crate) fn decompress_lzkn64(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::decompress(&bytes)?.into()))
+    }
+
+    #[pyfunction]
+    pub(crate) fn compress_lzkn64(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::compress(&bytes)?.into()))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use crate::Crunch64Error;
+    use rstest::rstest;
+    use std::{fs, path::PathBuf};
+
+    // The test files were made by this encoder, not taken from real data
+    #[rstest]
+    fn test_decompression_regression(
+        #[files("../test_data/*.lzkn64")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let decompressed = super::decompress(&compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_compression_regression(
+        #[files("../test_data/*.lzkn64")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let compressed = super::compress(&decompressed_file)?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_commands() -> Result<(), Crunch64Error> {
+        let mut bytes = vec![0, 0, 0, 0];
+        // Literals "abc"
+        bytes.extend([0x83, b'a', b'b', b'c']);
+        // 4 bytes from 3 bytes back
+        bytes.extend([0x08, 0x03]);
+        // "x" repeated 3 times
+        bytes.extend([0xC1, b'x']);
+        // Zero repeated 2 times, then 0x22 times
+        bytes.extend([0xE0, 0xFF, 0x20]);
+        let size = bytes.len() as u32;
+        bytes[..4].copy_from_slice(&size.to_be_bytes());
+
+        let mut expected = b"abcabcaxxx".to_vec();
+        expected.extend([0; 0x24]);
+        assert_eq!(super::decompress(&bytes)?.as_ref(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_errors() {
+        assert_eq!(
+            super::decompress(&[0, 0, 0]),
+            Err(Crunch64Error::InvalidLzkn64Header)
+        );
+
+        // Compressed size bigger than the data
+        assert_eq!(
+            super::decompress(&[0, 0, 0, 8, 0x81, 0]),
+            Err(Crunch64Error::TruncatedInput)
+        );
+
+        // Unused command
+        assert_eq!(
+            super::decompress(&[0, 0, 0, 5, 0xA0]),
+            Err(Crunch64Error::InvalidCommand)
+        );
+
+        // Back-reference before the start of the data
+        assert_eq!(
+            super::decompress(&[0, 0, 0, 8, 0x81, b'a', 0x00, 0x02]),
+            Err(Crunch64Error::InvalidBackReference)
+        );
+    }
+
+    #[rstest]
+    fn test_long_runs(#[values(0x00, 0x55)] byte: u8) -> Result<(), Crunch64Error> {
+        let mut data = vec![1, 2, 3];
+        data.extend([byte; 0x500]);
+        data.extend([4, 5]);
+
+        let compressed = super::compress(&data)?;
+        assert_eq!(super::decompress(&compressed)?.as_ref(), data);
+        Ok(())
+    }
+}
@@ -0,0 +1,363 @@
+// Implements the compression used by Rare's N64 games (e.g. Banjo-Kazooie).
+// Each compressed file starts with a 6-byte header containing the magic 0x1172
+// and the big-endian uncompressed size, followed by a raw DEFLATE stream
+// produced by gzip. The gzip footer is not present.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{gzip, utils, Codec, Crunch64Error};
+
+const HEADER_SIZE: usize = 6;
+
+fn parse_header(bytes: &[u8]) -> Result<usize, Crunch64Error> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(Crunch64Error::InvalidRareHeader);
+    }
+
+    if bytes[0..2] != [0x11, 0x72] {
+        return Err(Crunch64Error::InvalidRareHeader);
+    }
+
+    let decompressed_size = utils::read_u32(&bytes[2..], 0)? as usize;
+
+    Ok(decompressed_size)
+}
+
+fn write_header(dst: &mut Vec<u8>, uncompressed_size: usize) {
+    dst.extend([0x11, 0x72]);
+    dst.extend((uncompressed_size as u32).to_be_bytes());
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data,
+/// including the header
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let decompressed_size = parse_header(bytes)?;
+
+    // Compressed files are usually padded, so ignore any data after the stream
+    let (decompressed, stream_size) = gzip::decompress_raw_with_size(&bytes[HEADER_SIZE..])?;
+    if decompressed.len() != decompressed_size {
+        return Err(Crunch64Error::SizeMismatch);
+    }
+
+    Ok((decompressed, HEADER_SIZE + stream_size))
+}
+
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let decompressed_size = parse_header(bytes)?;
+
+    let dst = dst
+        .get_mut(..decompressed_size)
+        .ok_or(Crunch64Error::OutOfBounds)?;
+    match gzip::decompress_raw_into(&bytes[HEADER_SIZE..], dst) {
+        Ok(size) if size == decompressed_size => Ok(size),
+        // Running out of room means the stream is bigger than the header says
+        Ok(_) | Err(Crunch64Error::OutOfBounds) => Err(Crunch64Error::SizeMismatch),
+        Err(e) => Err(e),
+    }
+}
+
+fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
+    // Same as gzip, with a 6-byte header instead of an 8-byte footer
+    Ok(gzip::size_for_compressed_buffer(input_size)? - 2)
+}
+
+pub fn compress(bytes: &[u8], level: usize, small_mem: bool) -> Result<Box<[u8]>, Crunch64Error> {
+    let mut output: Vec<u8> = Vec::with_capacity(size_for_compressed_buffer(bytes.len())?);
+
+    write_header(&mut output, bytes.len());
+    output.extend(gzip::compress_raw(bytes, level, small_mem)?.iter());
+
+    Ok(output.into_boxed_slice())
+}
+
+/// Rare [`Codec`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rare {
+    /// Compression level (1-9)
+    pub level: usize,
+    /// Output compressed blocks more often
+    pub small_mem: bool,
+}
+
+impl Rare {
+    pub const DEFAULT: Rare = Rare {
+        level: 9,
+        small_mem: false,
+    };
+}
+
+impl Default for Rare {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Codec for Rare {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress(bytes, self.level, self.small_mem)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        parse_header(bytes)
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        parse_header(bytes)?;
+        Ok(HEADER_SIZE)
+    }
+}
+
+#[cfg(feature = "c_bindings")]
+mod c_bindings {
+    use core::ffi::c_int;
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_rare_decompress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if src_len < super::HEADER_SIZE {
+            return super::Crunch64Error::OutOfBounds;
+        }
+
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(super::HEADER_SIZE, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::parse_header(bytes) {
+            Err(e) => return e,
+            Ok(value) => unsafe { *dst_size = value },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_rare_decompress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_rare_compress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        match super::size_for_compressed_buffer(src_len) {
+            Err(e) => return e,
+            Ok(uncompressed_size) => unsafe { *dst_size = uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_rare_compress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+        level: c_int,
+        small_mem: bool,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let data = match super::compress(bytes, level as usize, small_mem) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+}
+
+#[cfg(feature = "python_bindings")]
+pub(crate) mod python_bindings {
+    use pyo3::prelude::*;
+    use std::borrow::Cow;
+
+    /**
+     * We use a `Cow` instead of a plain &[u8] because the latter only allows Python's
+     * `bytes` objects, while `Cow`` allows for both `bytes` and `bytearray`.
+     * This is important because an argument typed as `bytes` allows to pass a
+     * `bytearray` object too.
+     */
+
+    #[pyfunction]
+    pub(crate) fn decompress_rare(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::decompress(&bytes)?.into()))
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (bytes, level=9, small_mem=false))]
+    pub(crate) fn compress_rare(
+        bytes: Cow<[u8]>,
+        level: usize,
+        small_mem: bool,
+    ) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(
+            super::compress(&bytes, level, small_mem)?.into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use crate::Crunch64Error;
+    use core::panic;
+    use rstest::rstest;
+    use std::{
+        fs::File,
+        io::{BufReader, Read},
+        path::PathBuf,
+    };
+
+    pub fn read_test_file(path: PathBuf) -> Vec<u8> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(_error) => {
+                panic!("Failed to open file");
+            }
+        };
+
+        let mut buf_reader = BufReader::new(file);
+        let mut buffer = Vec::new();
+
+        let _ = buf_reader.read_to_end(&mut buffer);
+
+        buffer
+    }
+
+    #[rstest]
+    fn test_matching_decompression(
+        #[files("../test_data/*.rare")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let decompressed = super::decompress(compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_bad_header() {
+        let mut compressed_file = read_test_file(PathBuf::from("../test_data/small.txt.rare"));
+        compressed_file[1] ^= 0xFF;
+
+        assert_eq!(
+            super::decompress(&compressed_file),
+            Err(Crunch64Error::InvalidRareHeader)
+        );
+    }
+
+    #[rstest]
+    #[case::smaller(-1)]
+    #[case::larger(1)]
+    fn test_decompression_size_mismatch(#[case] delta: i32) {
+        let mut compressed_file = read_test_file(PathBuf::from("../test_data/small.txt.rare"));
+        let size = u32::from_be_bytes(compressed_file[2..6].try_into().unwrap());
+        compressed_file[2..6].copy_from_slice(&size.wrapping_add_signed(delta).to_be_bytes());
+
+        assert_eq!(
+            super::decompress(&compressed_file),
+            Err(Crunch64Error::SizeMismatch)
+        );
+
+        let mut dst = vec![0; 0x1000];
+        assert_eq!(
+            super::decompress_into(&compressed_file, &mut dst),
+            Err(Crunch64Error::SizeMismatch)
+        );
+    }
+
+    #[test]
+    fn test_decompression_padded() -> Result<(), Crunch64Error> {
+        let mut compressed_file = read_test_file(PathBuf::from("../test_data/small.txt.rare"));
+        let decompressed_file = read_test_file(PathBuf::from("../test_data/small.txt"));
+        compressed_file.resize(compressed_file.len().next_multiple_of(0x10) + 0x10, 0xAA);
+
+        let decompressed = super::decompress(&compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_matching_compression(
+        #[files("../test_data/*.rare")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let compressed = super::compress(decompressed_file.as_slice(), 9, false)?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+}
@@ -0,0 +1,384 @@
+// Implements the run-length encoding of the GBA/DS BIOS. After the same
+// header as LZ77 with type 0x30, each block starts with a flag byte. If its
+// top bit is set, the next byte is repeated (flag & 0x7F) + 3 times, otherwise
+// (flag & 0x7F) + 1 literal bytes follow.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{utils, Codec, Crunch64Error};
+
+const MIN_RUN: usize = 3;
+const MAX_RUN: usize = 0x82;
+const MAX_LITERALS: usize = 0x80;
+
+/// Fields of the RLE header, 4 bytes long or 8 bytes if the size doesn't fit
+/// in 24 bits
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RleHeader {
+    pub uncompressed_size: usize,
+}
+
+impl RleHeader {
+    pub fn parse(bytes: &[u8]) -> Result<RleHeader, Crunch64Error> {
+        if bytes.first() != Some(&0x30) {
+            return Err(Crunch64Error::InvalidRleHeader);
+        }
+
+        let (uncompressed_size, _) =
+            utils::parse_bios_header_size(bytes).ok_or(Crunch64Error::InvalidRleHeader)?;
+
+        Ok(RleHeader { uncompressed_size })
+    }
+
+    /// Size of the header in bytes
+    pub fn size(&self) -> usize {
+        utils::bios_header_size(self.uncompressed_size)
+    }
+
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        utils::write_bios_header(dst, 0x30, self.uncompressed_size);
+    }
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data,
+/// which can be followed by unrelated data in `bytes`
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let header = RleHeader::parse(bytes)?;
+
+    let mut ret = utils::decompression_buffer(header.uncompressed_size, bytes.len());
+    let compressed_size = decompress_to(bytes, &header, &mut ret)?;
+
+    Ok((ret.into_boxed_slice(), compressed_size))
+}
+
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let header = RleHeader::parse(bytes)?;
+
+    let dst = dst
+        .get_mut(..header.uncompressed_size)
+        .ok_or(Crunch64Error::OutOfBounds)?;
+    decompress_to(bytes, &header, &mut utils::SliceOutput::new(dst))?;
+
+    Ok(header.uncompressed_size)
+}
+
+fn decompress_to<O: utils::Output>(
+    bytes: &[u8],
+    header: &RleHeader,
+    ret: &mut O,
+) -> Result<usize, Crunch64Error> {
+    let decompressed_size = header.uncompressed_size;
+    let mut input_pos = header.size();
+
+    while ret.len() < decompressed_size {
+        let flag = utils::next_u8(bytes, &mut input_pos)? as usize;
+
+        if flag & 0x80 != 0 {
+            let length = (flag & 0x7F) + MIN_RUN;
+            if ret.len() + length > decompressed_size {
+                return Err(Crunch64Error::OutputOverrun);
+            }
+
+            let value = utils::next_u8(bytes, &mut input_pos)?;
+            for _ in 0..length {
+                ret.push(value)?;
+            }
+        } else {
+            let length = flag + 1;
+            if ret.len() + length > decompressed_size {
+                return Err(Crunch64Error::OutputOverrun);
+            }
+
+            for _ in 0..length {
+                ret.push(utils::next_u8(bytes, &mut input_pos)?)?;
+            }
+        }
+    }
+
+    Ok(input_pos)
+}
+
+fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
+    // Every byte as a literal, plus a flag byte for every 0x80 of them
+    Ok(8 + input_size + input_size.div_ceil(MAX_LITERALS))
+}
+
+fn flush_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        output.push((chunk.len() - 1) as u8);
+        output.extend(chunk);
+    }
+}
+
+/// Compresses `bytes`, turning every run of at least 3 bytes into a run block.
+/// The output isn't known to match Nintendo's encoder.
+pub fn compress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    let mut output = Vec::with_capacity(size_for_compressed_buffer(bytes.len())?);
+    RleHeader {
+        uncompressed_size: bytes.len(),
+    }
+    .write(&mut output);
+
+    let mut literals_start = 0;
+    let mut input_pos = 0;
+    while input_pos < bytes.len() {
+        let value = bytes[input_pos];
+        let run_length = bytes[input_pos..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == value)
+            .count();
+
+        if run_length < MIN_RUN {
+            input_pos += run_length;
+            continue;
+        }
+
+        flush_literals(&mut output, &bytes[literals_start..input_pos]);
+        output.extend([(0x80 | (run_length - MIN_RUN)) as u8, value]);
+        input_pos += run_length;
+        literals_start = input_pos;
+    }
+    flush_literals(&mut output, &bytes[literals_start..]);
+
+    Ok(output.into_boxed_slice())
+}
+
+/// RLE [`Codec`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rle;
+
+impl Rle {
+    pub const DEFAULT: Rle = Rle;
+}
+
+impl Codec for Rle {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress(bytes)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Ok(RleHeader::parse(bytes)?.uncompressed_size)
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Ok(RleHeader::parse(bytes)?.size())
+    }
+}
+
+#[cfg(feature = "c_bindings")]
+mod c_bindings {
+    #[no_mangle]
+    pub extern "C" fn crunch64_rle_decompress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::RleHeader::parse(bytes) {
+            Err(e) => return e,
+            Ok(header) => unsafe { *dst_size = header.uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_rle_decompress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_rle_compress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        match super::size_for_compressed_buffer(src_len) {
+            Err(e) => return e,
+            Ok(uncompressed_size) => unsafe { *dst_size = uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_rle_compress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let data = match super::compress(bytes) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+}
+
+#[cfg(feature = "python_bindings")]
+pub(crate) mod python_bindings {
+    use pyo3::prelude::*;
+    use std::borrow::Cow;
+
+    /**
+     * We use a `Cow` instead of a plain &[u8] because the latter only allows Python's
+     * `bytes` objects, while `Cow`` allows for both `bytes` and `bytearray`.
+     * This is important because an argument typed as `bytes` allows to pass a
+     * `bytearray` object too.
+     */
+
+    #[pyfunction]
+    pub(crate) fn decompress_rle(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::decompress(&bytes)?.into()))
+    }
+
+    #[pyfunction]
+    pub(crate) fn compress_rle(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::compress(&bytes)?.into()))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use crate::Crunch64Error;
+    use rstest::rstest;
+    use std::{fs, path::PathBuf};
+
+    // The test files were made by this encoder, not taken from real data
+    #[rstest]
+    fn test_decompression_regression(
+        #[files("../test_data/*.rle")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let decompressed = super::decompress(&compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_compression_regression(
+        #[files("../test_data/*.rle")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let compressed = super::compress(&decompressed_file)?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocks() -> Result<(), Crunch64Error> {
+        let mut data = vec![b'a', b'b', b'b'];
+        data.extend([b'c'; 0x83]);
+        data.extend(0..0x81);
+
+        let compressed = super::compress(&data)?;
+        #[rustfmt::skip]
+        let expected_start = [
+            0x30, 0x07, 0x01, 0x00,
+            // 3 literals, then a run of 0x82 and the first block of 0x80 literals
+            0x02, b'a', b'b', b'b',
+            0xFF, b'c',
+            0x7F, b'c', 0x00, 0x01,
+        ];
+        assert_eq!(compressed[..expected_start.len()], expected_start);
+        assert_eq!(super::decompress(&compressed)?.as_ref(), data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_errors() {
+        assert_eq!(
+            super::decompress(&[0x10, 0x01, 0x00, 0x00, 0x00, 0x00]),
+            Err(Crunch64Error::InvalidRleHeader)
+        );
+
+        // Run longer than the size in the header
+        assert_eq!(
+            super::decompress(&[0x30, 0x02, 0x00, 0x00, 0x80, b'a']),
+            Err(Crunch64Error::OutputOverrun)
+        );
+
+        assert_eq!(
+            super::decompress(&[0x30, 0x04, 0x00, 0x00, 0x03, b'a']),
+            Err(Crunch64Error::TruncatedInput)
+        );
+    }
+}
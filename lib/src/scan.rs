@@ -0,0 +1,246 @@
+//! Finding compressed data embedded in a larger file, like a ROM image.
+//!
+//! Formats with a header are found by looking for their magic at every
+//! offset. gzip data without a header and raw DEFLATE streams have no magic,
+//! so [`ScanOptions::headerless`] has to try decompressing at every offset
+//! instead.
+
+use alloc::vec::Vec;
+
+use crate::{gzip, Codec, Format};
+
+/// Raw DEFLATE streams shorter than this are too likely to be random data
+const MIN_DEFLATE_SIZE: usize = 0x40;
+
+/// Options for [`scan_with_options`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ScanOptions {
+    /// Only look for compressed data at offsets that are a multiple of this
+    pub alignment: usize,
+    /// Also look for gzip data without a header and raw DEFLATE streams. This
+    /// is much slower, and raw DEFLATE streams can't be told apart from
+    /// random data as reliably as the other formats. They are only searched
+    /// for with an alignment of at least 4, and only accepted if they are at
+    /// least 0x40 bytes long, end with zero padding bits and are followed by
+    /// zeros up to the alignment.
+    pub headerless: bool,
+}
+
+impl ScanOptions {
+    pub const DEFAULT: ScanOptions = ScanOptions {
+        alignment: 1,
+        headerless: false,
+    };
+}
+
+impl Default for ScanOptions {
+    fn default() -> ScanOptions {
+        ScanOptions::DEFAULT
+    }
+}
+
+/// Compressed data found by [`scan`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Blob {
+    pub offset: usize,
+    pub format: Format,
+    pub compressed_size: usize,
+    pub decompressed_size: usize,
+}
+
+/// Finds the compressed data in `rom` with the default options
+pub fn scan(rom: &[u8]) -> Vec<Blob> {
+    scan_with_options(rom, &ScanOptions::DEFAULT)
+}
+
+/// Finds the compressed data in `rom`. Every candidate is decompressed to
+/// make sure it's valid, and the search resumes after the end of each blob
+/// that is found.
+pub fn scan_with_options(rom: &[u8], options: &ScanOptions) -> Vec<Blob> {
+    let alignment = options.alignment.max(1);
+
+    let mut blobs = Vec::new();
+    let mut offset = 0;
+
+    while offset < rom.len() {
+        match find_blob(&rom[offset..], alignment, options) {
+            Some((format, compressed_size, decompressed_size)) => {
+                blobs.push(Blob {
+                    offset,
+                    format,
+                    compressed_size,
+                    decompressed_size,
+                });
+                offset += compressed_size.next_multiple_of(alignment);
+            }
+            None => offset += alignment,
+        }
+    }
+
+    blobs
+}
+
+// Checks whether `bytes` starts with compressed data, returning its format,
+// compressed size and decompressed size
+fn find_blob(
+    bytes: &[u8],
+    alignment: usize,
+    options: &ScanOptions,
+) -> Option<(Format, usize, usize)> {
+    let has_header = |format: Format| match format {
+        // gzip data is only recognized by its header here, since the header is optional
+        Format::Gzip => bytes.starts_with(&[0x1F, 0x8B]),
+        format => format.header_size(bytes).is_ok(),
+    };
+
+    let format = [
+        Format::Yay0,
+        Format::Yaz0,
+        Format::Mio0,
+        Format::Rare,
+        Format::Vpk0,
+        Format::Gzip,
+        Format::Zlib,
+    ]
+    .into_iter()
+    .find(|&format| has_header(format));
+
+    if let Some(format) = format {
+        let (decompressed, compressed_size) = format.decompress_with_size(bytes).ok()?;
+        return Some((format, compressed_size, decompressed.len()));
+    }
+
+    if !options.headerless {
+        return None;
+    }
+
+    let (decompressed, stream_size) = Format::Deflate.decompress_with_size(bytes).ok()?;
+    // An empty stream followed by zeros is a valid gzip footer, and is common
+    // in code and padding
+    if decompressed.is_empty() {
+        return None;
+    }
+    if gzip::footer_matches(&bytes[stream_size..], &decompressed) {
+        return Some((Format::Gzip, stream_size + 8, decompressed.len()));
+    }
+
+    // Without a checksum, only accept streams that look like real compressed
+    // data and are padded with zeros up to the alignment, like files in a ROM.
+    // Random data decodes as a short DEFLATE stream too often to look at
+    // offsets that are less than 4 bytes apart.
+    if alignment < 4 {
+        return None;
+    }
+    let padding = bytes
+        .get(stream_size..stream_size.next_multiple_of(alignment))
+        .unwrap_or(&[]);
+    if stream_size < MIN_DEFLATE_SIZE
+        || decompressed.len() <= stream_size
+        || !padding.iter().all(|&b| b == 0)
+    {
+        return None;
+    }
+
+    // Random data that decodes as DEFLATE usually has some of the bits after
+    // the final block set, which an encoder never does
+    gzip::inflate_zero_padded(bytes).ok()?;
+    Some((Format::Deflate, stream_size, decompressed.len()))
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use super::{Blob, ScanOptions};
+    use crate::{Codec, Format};
+    use std::{fs, path::PathBuf};
+
+    // Places data compressed in every format that can be scanned for at
+    // 0x1000-byte intervals. LZKN64 only stores its compressed size, HAL LZ
+    // has no header and LZ77, RLE and Huffman only have a 1-byte magic, so they
+    // can't be told apart from other data.
+    fn build_rom(decompressed: &[u8], filler: impl Fn(usize) -> u8) -> (Vec<u8>, Vec<Blob>) {
+        let mut rom = Vec::new();
+        let mut blobs = Vec::new();
+
+        for format in Format::ALL {
+            if matches!(
+                format,
+                Format::Lzkn64 | Format::Lz77 | Format::Rle | Format::Huffman
+            ) {
+                continue;
+            }
+            rom.extend((rom.len()..rom.len() + 0x1000).map(&filler));
+
+            let compressed = format.compress(decompressed).unwrap();
+            blobs.push(Blob {
+                offset: rom.len(),
+                format,
+                compressed_size: compressed.len(),
+                decompressed_size: decompressed.len(),
+            });
+            rom.extend(compressed.iter());
+
+            // Pad to the next 0x10 bytes
+            rom.resize(rom.len().next_multiple_of(0x10), 0);
+        }
+        rom.extend((rom.len()..rom.len() + 0x1000).map(&filler));
+
+        (rom, blobs)
+    }
+
+    #[test]
+    fn test_scan() {
+        let decompressed = fs::read(PathBuf::from("../test_data/small.txt")).unwrap();
+        let (rom, blobs) = build_rom(&decompressed, |_| 0);
+
+        let options = ScanOptions {
+            alignment: 0x10,
+            headerless: true,
+        };
+        assert_eq!(super::scan_with_options(&rom, &options), blobs);
+
+        // Only the formats with a header are found by default
+        let with_header: Vec<Blob> = blobs
+            .into_iter()
+            .filter(|blob| blob.format != Format::Deflate)
+            .filter(|blob| blob.format != Format::Gzip)
+            .collect();
+        assert_eq!(super::scan(&rom), with_header);
+    }
+
+    #[test]
+    fn test_scan_code() {
+        // Blobs surrounded by real code instead of zeros
+        let code = fs::read(PathBuf::from("../test_data/mips_gist_wiseguy_yaz0.bin")).unwrap();
+        let decompressed = fs::read(PathBuf::from("../test_data/small.txt")).unwrap();
+        let (rom, blobs) = build_rom(&decompressed, |i| code[i % code.len()]);
+
+        let options = ScanOptions {
+            alignment: 0x10,
+            headerless: true,
+        };
+        assert_eq!(super::scan_with_options(&rom, &options), blobs);
+    }
+
+    #[test]
+    fn test_scan_random() {
+        // Noise from a xorshift generator
+        let mut state: u32 = 0x12345678;
+        let rom: Vec<u8> = (0..0x100000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+
+        for alignment in [1, 4, 0x10] {
+            let options = ScanOptions {
+                alignment,
+                headerless: true,
+            };
+            assert_eq!(super::scan_with_options(&rom, &options), []);
+        }
+    }
+}
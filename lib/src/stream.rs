@@ -0,0 +1,301 @@
+//! Adapters for compressing and decompressing through [`std::io`] streams.
+//!
+//! The Yaz0, Yay0 and MIO0 decoders (e.g. [`crate::yaz0::Yaz0Decoder`]) keep
+//! only the last 0x1000 decompressed bytes, since back-references can't reach
+//! further than that. Their encoders keep the same history plus a bounded
+//! amount of unparsed input:
+//!
+//! - [`crate::yaz0::Yaz0Encoder`] takes the uncompressed size up front, so it
+//!   can write the header first and each group of 8 layout bits as soon as it
+//!   is complete.
+//! - [`crate::yay0::Yay0Encoder`] and [`crate::mio0::Mio0Encoder`] keep the
+//!   layout bits, link table and chunks separately until the end, since the
+//!   header needs their sizes.
+//!
+//! Other formats can be written through [`Encoder`], which buffers all of the
+//! uncompressed data.
+
+use std::io::{self, Read, Write};
+
+use crate::{
+    utils::{self, Group},
+    Codec, Crunch64Error,
+};
+
+const HISTORY_SIZE: usize = 0x1000;
+const HISTORY_MASK: usize = HISTORY_SIZE - 1;
+
+// Number of new input bytes the streaming encoders collect before parsing them
+const PARSE_CHUNK_SIZE: usize = 0x10000;
+
+/// A [`Write`] adapter that compresses everything written to it with a [`Codec`].
+///
+/// The whole input is kept in memory and compressed by [`Encoder::finish`],
+/// which writes it to the inner writer. Dropping the encoder finishes it too,
+/// but any error is lost.
+pub struct Encoder<W: Write, C: Codec> {
+    inner: Option<W>,
+    codec: C,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write, C: Codec + Default> Encoder<W, C> {
+    pub fn new(inner: W) -> Self {
+        Self::with_codec(inner, C::default())
+    }
+}
+
+impl<W: Write, C: Codec> Encoder<W, C> {
+    pub fn with_codec(inner: W, codec: C) -> Self {
+        Encoder {
+            inner: Some(inner),
+            codec,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// Compresses the data written so far, writes it and returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    fn try_finish(&mut self) -> io::Result<()> {
+        if let Some(inner) = self.inner.as_mut() {
+            let compressed = self.codec.compress(&self.buffer)?;
+            self.buffer = Vec::new();
+            inner.write_all(&compressed)?;
+            inner.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write, C: Codec> Write for Encoder<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Nothing can be written before the whole input is known
+        Ok(())
+    }
+}
+
+impl<W: Write, C: Codec> Drop for Encoder<W, C> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.try_finish();
+        }
+    }
+}
+
+/// The greedy parser of the Yaz0, Yay0 and MIO0 encoders, fed with the input
+/// a piece at a time. It produces the same groups as [`utils::parse_greedy`]
+/// while keeping only the last 0x1000 parsed bytes.
+pub(crate) struct GreedyStream {
+    // History followed by the input that hasn't been parsed yet
+    buffer: Vec<u8>,
+    // Position of the next group in `buffer`
+    position: usize,
+    max_match_length: usize,
+    min_distance: usize,
+}
+
+impl GreedyStream {
+    pub(crate) fn new(max_match_length: usize, min_distance: usize) -> Self {
+        GreedyStream {
+            buffer: Vec::new(),
+            position: 0,
+            max_match_length,
+            min_distance,
+        }
+    }
+
+    /// Adds `bytes` to the input, calling `emit` for each group that can be
+    /// parsed so far with the input byte it starts with
+    pub(crate) fn push(&mut self, bytes: &[u8], mut emit: impl FnMut(Group, u8)) {
+        // Searching for a group reads the input up to the end of a match at the
+        // next position, plus the bytes hashed to find it
+        let lookahead = self.max_match_length + 4;
+
+        for piece in bytes.chunks(PARSE_CHUNK_SIZE) {
+            self.buffer.extend_from_slice(piece);
+            if self.buffer.len() >= self.position + PARSE_CHUNK_SIZE + lookahead {
+                self.parse(self.buffer.len() - lookahead, &mut emit);
+            }
+        }
+    }
+
+    /// Parses the rest of the input
+    pub(crate) fn finish(&mut self, mut emit: impl FnMut(Group, u8)) {
+        self.parse(self.buffer.len(), &mut emit);
+    }
+
+    // Parses the groups starting before `end`, and drops the input that is no
+    // longer needed as history
+    fn parse(&mut self, end: usize, emit: &mut impl FnMut(Group, u8)) {
+        let buffer = &self.buffer;
+        let mut group_pos = self.position;
+        let position = utils::parse_greedy_range(
+            buffer,
+            self.position..end,
+            self.max_match_length,
+            self.min_distance,
+            |group| {
+                emit(group, buffer[group_pos]);
+                group_pos += group.length();
+            },
+        );
+
+        let history_start = position.saturating_sub(HISTORY_SIZE);
+        self.buffer.drain(..history_start);
+        self.position = position - history_start;
+    }
+}
+
+/// The last 0x1000 bytes produced by a decoder, plus the back-reference
+/// currently being copied out of them
+pub(crate) struct History {
+    window: Box<[u8]>,
+    position: usize,
+    uncompressed_size: usize,
+    copy_distance: usize,
+    copy_remaining: usize,
+}
+
+impl History {
+    pub(crate) fn new(uncompressed_size: usize) -> Self {
+        History {
+            window: vec![0; HISTORY_SIZE].into_boxed_slice(),
+            position: 0,
+            uncompressed_size,
+            copy_distance: 0,
+            copy_remaining: 0,
+        }
+    }
+
+    /// Whether all of the uncompressed data has been produced
+    pub(crate) fn is_done(&self) -> bool {
+        self.position >= self.uncompressed_size
+    }
+
+    pub(crate) fn push(&mut self, byte: u8) -> u8 {
+        self.window[self.position & HISTORY_MASK] = byte;
+        self.position += 1;
+        byte
+    }
+
+    /// Starts copying a back-reference, with the same checks as the slice decoders
+    pub(crate) fn start_copy(
+        &mut self,
+        distance: usize,
+        length: usize,
+    ) -> Result<(), Crunch64Error> {
+        if distance == 0 || distance > self.position || distance > HISTORY_SIZE {
+            return Err(Crunch64Error::InvalidBackReference);
+        }
+        if self.position + length > self.uncompressed_size {
+            return Err(Crunch64Error::OutputOverrun);
+        }
+
+        self.copy_distance = distance;
+        self.copy_remaining = length;
+        Ok(())
+    }
+
+    /// Produces the next byte of the back-reference being copied, if any
+    pub(crate) fn next_copied(&mut self) -> Option<u8> {
+        if self.copy_remaining == 0 {
+            return None;
+        }
+
+        self.copy_remaining -= 1;
+        let byte = self.window[(self.position - self.copy_distance) & HISTORY_MASK];
+        Some(self.push(byte))
+    }
+}
+
+/// Fills `buf` by copying back-references out of `history` and calling
+/// `next_op` to decode the rest, until `buf` is full or the data is done.
+/// `next_op` returns the literal byte it decoded, or `None` if it started a
+/// back-reference.
+pub(crate) fn decode_into<F>(
+    history: &mut History,
+    buf: &mut [u8],
+    mut next_op: F,
+) -> io::Result<usize>
+where
+    F: FnMut(&mut History) -> io::Result<Option<u8>>,
+{
+    let mut written = 0;
+
+    while written < buf.len() {
+        if let Some(byte) = history.next_copied() {
+            buf[written] = byte;
+            written += 1;
+        } else if history.is_done() {
+            break;
+        } else if let Some(byte) = next_op(history)? {
+            buf[written] = history.push(byte);
+            written += 1;
+        }
+    }
+
+    Ok(written)
+}
+
+/// Reads a single byte, treating the end of the stream as truncated data
+pub(crate) fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0u8; 1];
+    match reader.read_exact(&mut byte) {
+        Ok(()) => Ok(byte[0]),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            Err(Crunch64Error::TruncatedInput.into())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads up to `size` bytes, stopping early only at the end of the stream
+pub(crate) fn read_up_to<R: Read>(reader: &mut R, size: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(size as u64).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encoder;
+    use crate::{gzip::Deflate, yaz0::Yaz0, Codec};
+    use std::io::Write;
+
+    #[test]
+    fn test_encoder_finish_on_drop() {
+        let data = b"crunch crunch crunch crunch";
+
+        let mut output = Vec::new();
+        {
+            let mut encoder = Encoder::<_, Yaz0>::new(&mut output);
+            encoder.write_all(&data[..10]).unwrap();
+            encoder.write_all(&data[10..]).unwrap();
+        }
+        assert_eq!(output, Yaz0::DEFAULT.compress(data).unwrap().as_ref());
+
+        let codec = Deflate {
+            level: 1,
+            small_mem: false,
+        };
+        let mut encoder = Encoder::with_codec(Vec::new(), codec);
+        encoder.write_all(data).unwrap();
+        assert_eq!(
+            encoder.finish().unwrap(),
+            codec.compress(data).unwrap().as_ref()
+        );
+    }
+}
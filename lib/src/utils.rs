@@ -1,4 +1,8 @@
-use core::cmp;
+use alloc::vec::Vec;
+use core::{
+    cmp,
+    ops::{Range, RangeInclusive},
+};
 
 use crate::Crunch64Error;
 
@@ -7,8 +11,8 @@ pub fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, Crunch64Error> {
         return Err(Crunch64Error::UnalignedRead);
     }
 
-    if offset + 2 >= bytes.len() {
-        return Err(Crunch64Error::OutOfBounds);
+    if offset + 2 > bytes.len() {
+        return Err(Crunch64Error::TruncatedInput);
     }
 
     match bytes[offset..offset + 2].try_into() {
@@ -23,7 +27,7 @@ pub fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, Crunch64Error> {
     }
 
     if offset + 4 > bytes.len() {
-        return Err(Crunch64Error::OutOfBounds);
+        return Err(Crunch64Error::TruncatedInput);
     }
 
     match bytes[offset..offset + 4].try_into() {
@@ -32,22 +36,181 @@ pub fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, Crunch64Error> {
     }
 }
 
+/// Reads the byte at `*index` and advances `index` past it
+pub(crate) fn next_u8(bytes: &[u8], index: &mut usize) -> Result<u8, Crunch64Error> {
+    let byte = *bytes.get(*index).ok_or(Crunch64Error::TruncatedInput)?;
+    *index += 1;
+    Ok(byte)
+}
+
+/// Creates the output buffer for a decompressor. The uncompressed size comes
+/// from the (untrusted) header, so the initial capacity is limited based on the
+/// size of the compressed data to avoid huge allocations for corrupt headers.
+pub(crate) fn decompression_buffer(uncompressed_size: usize, compressed_size: usize) -> Vec<u8> {
+    Vec::with_capacity(cmp::min(
+        uncompressed_size,
+        compressed_size.saturating_mul(0x10),
+    ))
+}
+
+/// Reads the uncompressed size from the header shared by the GBA/DS BIOS
+/// formats: 24 little-endian bits after the type byte, or the next 32 bits if
+/// those are all 0. Returns the size and the length of the header.
+pub(crate) fn parse_bios_header_size(bytes: &[u8]) -> Option<(usize, usize)> {
+    let short = bytes.get(..4)?;
+    let size = u32::from_le_bytes([short[1], short[2], short[3], 0]) as usize;
+    if size != 0 {
+        return Some((size, 4));
+    }
+
+    let extended = bytes.get(4..8)?;
+    let size = u32::from_le_bytes([extended[0], extended[1], extended[2], extended[3]]);
+    Some((size as usize, 8))
+}
+
+/// Length of the GBA/DS BIOS header for `uncompressed_size`
+pub(crate) fn bios_header_size(uncompressed_size: usize) -> usize {
+    if uncompressed_size == 0 || uncompressed_size > 0xFFFFFF {
+        8
+    } else {
+        4
+    }
+}
+
+/// Writes a GBA/DS BIOS header with the `kind` byte and `uncompressed_size`
+pub(crate) fn write_bios_header(dst: &mut Vec<u8>, kind: u8, uncompressed_size: usize) {
+    dst.push(kind);
+
+    if bios_header_size(uncompressed_size) == 8 {
+        dst.extend([0; 3]);
+        dst.extend((uncompressed_size as u32).to_le_bytes());
+    } else {
+        dst.extend(&(uncompressed_size as u32).to_le_bytes()[..3]);
+    }
+}
+
+/// Destination of a decompressor, either a growable `Vec` or a caller-provided
+/// slice
+pub(crate) trait Output {
+    /// The bytes written so far
+    fn written(&self) -> &[u8];
+
+    /// Appends `byte`, or fails with [`Crunch64Error::OutOfBounds`] if there is no room left
+    fn push(&mut self, byte: u8) -> Result<(), Crunch64Error>;
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<(), Crunch64Error> {
+        for &byte in bytes {
+            self.push(byte)?;
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.written().len()
+    }
+}
+
+impl Output for Vec<u8> {
+    fn written(&self) -> &[u8] {
+        self
+    }
+
+    fn push(&mut self, byte: u8) -> Result<(), Crunch64Error> {
+        Vec::push(self, byte);
+        Ok(())
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<(), Crunch64Error> {
+        Vec::extend_from_slice(self, bytes);
+        Ok(())
+    }
+}
+
+/// [`Output`] writing to the start of a slice
+pub(crate) struct SliceOutput<'a> {
+    bytes: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceOutput<'a> {
+    pub(crate) fn new(bytes: &'a mut [u8]) -> Self {
+        SliceOutput { bytes, len: 0 }
+    }
+}
+
+impl Output for SliceOutput<'_> {
+    fn written(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    fn push(&mut self, byte: u8) -> Result<(), Crunch64Error> {
+        *self
+            .bytes
+            .get_mut(self.len)
+            .ok_or(Crunch64Error::OutOfBounds)? = byte;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<(), Crunch64Error> {
+        self.bytes
+            .get_mut(self.len..self.len + bytes.len())
+            .ok_or(Crunch64Error::OutOfBounds)?
+            .copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+}
+
+/// Appends `length` bytes to `output`, copied from `distance` bytes before its
+/// end. The copy may overlap the bytes it produces. Fails instead of growing
+/// `output` past `max_size`.
+pub(crate) fn copy_back_reference<O: Output>(
+    output: &mut O,
+    distance: usize,
+    length: usize,
+    max_size: usize,
+) -> Result<(), Crunch64Error> {
+    if distance == 0 || distance > output.len() {
+        return Err(Crunch64Error::InvalidBackReference);
+    }
+    if output.len() + length > max_size {
+        return Err(Crunch64Error::OutputOverrun);
+    }
+
+    let start = output.len() - distance;
+    for i in 0..length {
+        let byte = output.written()[start + i];
+        output.push(byte)?;
+    }
+
+    Ok(())
+}
+
+/// Borrows `src_len` bytes from `src` without copying them
 #[cfg(feature = "c_bindings")]
-pub(crate) fn u8_vec_from_pointer_array(
+pub(crate) fn u8_slice_from_pointer_array<'a>(
     src_len: usize,
     src: *const u8,
-) -> Result<alloc::vec::Vec<u8>, Crunch64Error> {
+) -> Result<&'a [u8], Crunch64Error> {
     if src.is_null() {
         return Err(Crunch64Error::NullPointer);
     }
 
-    let mut bytes = alloc::vec::Vec::with_capacity(src_len);
+    Ok(unsafe { core::slice::from_raw_parts(src, src_len) })
+}
 
-    for i in 0..src_len {
-        bytes.push(unsafe { *src.add(i) });
+/// Borrows the `*dst_len` bytes at `dst` as a mutable slice
+#[cfg(feature = "c_bindings")]
+pub(crate) fn u8_slice_mut_from_pointer_array<'a>(
+    dst_len: *mut usize,
+    dst: *mut u8,
+) -> Result<&'a mut [u8], Crunch64Error> {
+    if dst_len.is_null() || dst.is_null() {
+        return Err(Crunch64Error::NullPointer);
     }
 
-    Ok(bytes)
+    Ok(unsafe { core::slice::from_raw_parts_mut(dst, *dst_len) })
 }
 
 #[cfg(feature = "c_bindings")]
@@ -183,6 +346,18 @@ impl Window<'_> {
     // at most `max_match_length` bytes long, returning the offset and length of the longest match found.
     // Successive searches can only be performed at increasing input positions.
     pub(crate) fn search(&mut self, input_pos: usize, max_match_length: usize) -> (u32, u32) {
+        self.search_within(input_pos, max_match_length, 1..=WINDOW_SIZE)
+    }
+
+    // Same as `search`, but only for matches starting a number of bytes back
+    // within `distances`, for formats with a window smaller than 0x1000 bytes
+    // or that can't copy from the last bytes written
+    pub(crate) fn search_within(
+        &mut self,
+        input_pos: usize,
+        max_match_length: usize,
+        distances: RangeInclusive<usize>,
+    ) -> (u32, u32) {
         if input_pos < self.input_pos {
             panic!("window moved backwards");
         } else if input_pos >= self.input.len() {
@@ -208,7 +383,8 @@ impl Window<'_> {
             let match_offset =
                 input_pos - 1 - (input_pos.wrapping_sub(pos as usize + 1) & WINDOW_MASK);
 
-            if self.input[input_pos] == self.input[match_offset]
+            if distances.contains(&(input_pos - match_offset))
+                && self.input[input_pos] == self.input[match_offset]
                 && self.input[input_pos + 1] == self.input[match_offset + 1]
                 && self.input[match_offset + best_len] == self.input[input_pos + best_len]
             {
@@ -233,3 +409,230 @@ impl Window<'_> {
         (best_offset as u32, best_len as u32)
     }
 }
+
+/// One unit of LZ-compressed data, as chosen by a parser. Each one takes a
+/// layout bit in the Yaz0, Yay0 and MIO0 formats.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Group {
+    /// Copies the next input byte
+    Literal,
+    /// Copies `length` bytes starting `offset + 1` bytes back
+    BackReference { offset: u16, length: u32 },
+}
+
+impl Group {
+    /// Number of input bytes covered by the group
+    pub(crate) fn length(self) -> usize {
+        match self {
+            Group::Literal => 1,
+            Group::BackReference { length, .. } => length as usize,
+        }
+    }
+}
+
+/// Layout bits, link table and chunks of Yay0 or MIO0 data, filled in one
+/// group at a time
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Tables {
+    /// Layout bits, as big-endian 32-bit words
+    pub(crate) layout: Vec<u8>,
+    pub(crate) links: Vec<u8>,
+    pub(crate) chunks: Vec<u8>,
+    layout_bit_count: usize,
+}
+
+impl Tables {
+    pub(crate) fn push_literal(&mut self, byte: u8) {
+        self.push_layout_bit(true);
+        self.chunks.push(byte);
+    }
+
+    pub(crate) fn push_link(&mut self, link: u16) {
+        self.push_layout_bit(false);
+        self.links.extend(link.to_be_bytes());
+    }
+
+    fn push_layout_bit(&mut self, is_literal: bool) {
+        if self.layout_bit_count % 32 == 0 {
+            self.layout.extend([0; 4]);
+        }
+        if is_literal {
+            self.layout[self.layout_bit_count / 8] |= 0x80 >> (self.layout_bit_count % 8);
+        }
+        self.layout_bit_count += 1;
+    }
+
+    pub(crate) fn link_table_offset(&self) -> usize {
+        0x10 + self.layout.len()
+    }
+
+    pub(crate) fn chunk_offset(&self) -> usize {
+        self.link_table_offset() + self.links.len()
+    }
+}
+
+// Splits the input into groups like the reference encoders: the longest match
+// is taken unless the match at the next position is at least 2 bytes longer,
+// in which case a literal is emitted first. Only matches starting at least
+// `min_distance` bytes back are used.
+pub(crate) fn parse_greedy(
+    input: &[u8],
+    max_match_length: usize,
+    min_distance: usize,
+) -> Vec<Group> {
+    let mut groups = Vec::new();
+    parse_greedy_range(
+        input,
+        0..input.len(),
+        max_match_length,
+        min_distance,
+        |group| groups.push(group),
+    );
+    groups
+}
+
+// Same as `parse_greedy`, but only for the groups starting in `range`, with the
+// bytes before it as history. The last group can extend past the end of the
+// range. Returns the input position after the last group.
+pub(crate) fn parse_greedy_range(
+    input: &[u8],
+    range: Range<usize>,
+    max_match_length: usize,
+    min_distance: usize,
+    mut emit: impl FnMut(Group),
+) -> usize {
+    let mut window = Window::new(input);
+    let distances = min_distance..=WINDOW_SIZE;
+    let mut input_pos = range.start;
+
+    while input_pos < range.end {
+        let (mut group_pos, mut group_size) =
+            window.search_within(input_pos, max_match_length, distances.clone());
+
+        // If the group isn't larger than 2 bytes, copying the input without compression is smaller
+        if group_size <= 2 {
+            emit(Group::Literal);
+            input_pos += 1;
+            continue;
+        }
+
+        // Search for a new group after one position after the current one
+        let (new_position, new_size) =
+            window.search_within(input_pos + 1, max_match_length, distances.clone());
+
+        // If the new group is better than the current group by at least 2 bytes, use it instead
+        if new_size >= group_size + 2 {
+            emit(Group::Literal);
+            input_pos += 1;
+
+            group_size = new_size;
+            group_pos = new_position;
+        }
+
+        emit(Group::BackReference {
+            offset: (input_pos - group_pos as usize - 1) as u16,
+            length: group_size,
+        });
+
+        // Move forward in the input by the size of the group
+        input_pos += group_size as usize;
+    }
+
+    input_pos
+}
+
+// Splits the input into the groups that take the fewest bits, where a literal
+// takes 9 bits (a byte and its layout bit) and a back-reference takes
+// `match_cost(length)` bits.
+//
+// The cost of a back-reference doesn't depend on its offset, and every prefix
+// of a match is a match too, so the longest match at each position is enough
+// to know every group that can start there. The cheapest parse is then found
+// by dynamic programming over the input positions.
+pub(crate) fn parse_optimal<F>(input: &[u8], max_match_length: usize, match_cost: F) -> Vec<Group>
+where
+    F: Fn(usize) -> usize,
+{
+    const LITERAL_COST: usize = 9;
+
+    let input_size = input.len();
+
+    let mut window = Window::new(input);
+    let longest_matches: Vec<(u32, u32)> = (0..input_size)
+        .map(|input_pos| window.search(input_pos, max_match_length))
+        .collect();
+
+    // Cheapest cost of the first `i` bytes, and the length of the last group
+    // in that parse (1 for a literal)
+    let mut costs = vec![usize::MAX; input_size + 1];
+    let mut lengths = vec![0u16; input_size + 1];
+    costs[0] = 0;
+
+    for input_pos in 0..input_size {
+        let cost = costs[input_pos];
+
+        if cost + LITERAL_COST < costs[input_pos + 1] {
+            costs[input_pos + 1] = cost + LITERAL_COST;
+            lengths[input_pos + 1] = 1;
+        }
+
+        let (_, match_length) = longest_matches[input_pos];
+        for length in MIN_MATCH..=match_length as usize {
+            let end = input_pos + length;
+            if cost + match_cost(length) < costs[end] {
+                costs[end] = cost + match_cost(length);
+                lengths[end] = length as u16;
+            }
+        }
+    }
+
+    let mut groups = Vec::new();
+    let mut input_pos = input_size;
+
+    while input_pos > 0 {
+        let length = lengths[input_pos] as usize;
+        input_pos -= length;
+
+        if length == 1 {
+            groups.push(Group::Literal);
+        } else {
+            let (match_pos, _) = longest_matches[input_pos];
+            groups.push(Group::BackReference {
+                offset: (input_pos - match_pos as usize - 1) as u16,
+                length: length as u32,
+            });
+        }
+    }
+
+    groups.reverse();
+    groups
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+pub(crate) mod tests {
+    use alloc::vec::Vec;
+
+    // Noise from a xorshift generator, with a few copies of earlier data mixed
+    // in so that both literals and back-references are used
+    pub(crate) fn synthetic_data(size: usize) -> Vec<u8> {
+        let mut state: u32 = 0x12345678;
+        let mut data = Vec::with_capacity(size);
+        while data.len() < size {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let byte = state as u8;
+            if byte < 0x10 && data.len() > 0x100 {
+                let start = data.len() - 1 - (state >> 24) as usize;
+                for i in 0..((state >> 8) & 0xF) as usize + 3 {
+                    data.push(data[start + i]);
+                }
+            } else {
+                data.push(byte);
+            }
+        }
+        data.truncate(size);
+        data
+    }
+}
@@ -0,0 +1,784 @@
+// Implements the vpk0 compression used by Super Smash Bros. 64 and Pokémon
+// Snap. It's an LZSS variant where the whole file is a big-endian bitstream:
+// after a 9-byte header come two Huffman trees, one for back-reference
+// offsets and one for lengths, and then the data. The leaves of the trees
+// aren't values but bit sizes, so a value is read as a Huffman code followed
+// by that many bits.
+//
+// The decoder follows the format's description and the encoder doesn't try
+// to match the original one. Neither has been checked against files from the
+// games.
+
+use alloc::{boxed::Box, collections::BinaryHeap, vec::Vec};
+use core::{cmp::Reverse, iter};
+
+use crate::{
+    utils::{self, Group},
+    Codec, Crunch64Error,
+};
+
+const MAX_MATCH_LENGTH: usize = 0xFFFF;
+
+// Leaves can't read more bits than fit in a value
+const MAX_BIT_SIZE: usize = 32;
+
+/// How back-reference offsets are encoded
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Method {
+    /// Each offset is a single value
+    OneSample,
+    /// Offsets that are a multiple of 4 are stored divided by 4, and the
+    /// others are split into their remainder and quotient. This is the method
+    /// used by most files.
+    #[default]
+    TwoSample,
+}
+
+impl Method {
+    /// Lowercase name of the method
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::OneSample => "one-sample",
+            Method::TwoSample => "two-sample",
+        }
+    }
+}
+
+/// Fields of the 9-byte vpk0 header
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Vpk0Header {
+    pub uncompressed_size: usize,
+    pub method: Method,
+}
+
+impl Vpk0Header {
+    pub const SIZE: usize = 9;
+
+    pub fn parse(bytes: &[u8]) -> Result<Vpk0Header, Crunch64Error> {
+        if bytes.len() < Self::SIZE {
+            return Err(Crunch64Error::InvalidVpk0Header);
+        }
+
+        if &bytes[0..4] != b"vpk0" {
+            return Err(Crunch64Error::InvalidVpk0Header);
+        }
+
+        let method = match bytes[8] {
+            0 => Method::OneSample,
+            1 => Method::TwoSample,
+            _ => return Err(Crunch64Error::InvalidVpk0Header),
+        };
+
+        Ok(Vpk0Header {
+            uncompressed_size: utils::read_u32(bytes, 4)? as usize,
+            method,
+        })
+    }
+
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        dst.extend(b"vpk0");
+        dst.extend((self.uncompressed_size as u32).to_be_bytes());
+        dst.push(match self.method {
+            Method::OneSample => 0,
+            Method::TwoSample => 1,
+        });
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    // Position in bits
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        BitReader { bytes, pos: 0 }
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Crunch64Error> {
+        let byte = self
+            .bytes
+            .get(self.pos / 8)
+            .ok_or(Crunch64Error::TruncatedInput)?;
+        let bit = byte & (0x80 >> (self.pos % 8)) != 0;
+        self.pos += 1;
+        Ok(bit)
+    }
+
+    fn read_bits(&mut self, count: usize) -> Result<u32, Crunch64Error> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()? as u32;
+        }
+        Ok(value)
+    }
+
+    // Number of bytes used so far, including the last partial one
+    fn bytes_read(&self) -> usize {
+        self.pos.div_ceil(8)
+    }
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    // Number of bits used in the last byte, or 0 if it's full
+    used: usize,
+}
+
+impl BitWriter {
+    fn new(bytes: Vec<u8>) -> Self {
+        BitWriter { bytes, used: 0 }
+    }
+
+    fn write_bits(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if self.used == 0 {
+                self.bytes.push(0);
+            }
+            if value & (1 << i) != 0 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> self.used;
+            }
+            self.used = (self.used + 1) % 8;
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Node {
+    /// Number of bits of the value
+    Leaf(u8),
+    /// Indices of the nodes for the bits 0 and 1
+    Branch(usize, usize),
+}
+
+/// Huffman tree of bit sizes. Children always come before their parent in
+/// `nodes`, and the root is the last node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    // The tree is stored in post-order: a 0 bit followed by 8 bits is a leaf,
+    // and a 1 bit joins the last two subtrees into a branch. A 1 bit with fewer
+    // than two subtrees ends the tree.
+    fn read(reader: &mut BitReader) -> Result<Tree, Crunch64Error> {
+        let mut nodes = Vec::new();
+        let mut subtrees = Vec::new();
+
+        loop {
+            if reader.read_bit()? {
+                if subtrees.len() < 2 {
+                    break;
+                }
+                let one = subtrees.pop().unwrap();
+                let zero = subtrees.pop().unwrap();
+                nodes.push(Node::Branch(zero, one));
+            } else {
+                nodes.push(Node::Leaf(reader.read_bits(8)? as u8));
+            }
+            subtrees.push(nodes.len() - 1);
+        }
+
+        Ok(Tree { nodes })
+    }
+
+    fn write(&self, writer: &mut BitWriter) {
+        fn write_node(nodes: &[Node], index: usize, writer: &mut BitWriter) {
+            match nodes[index] {
+                Node::Leaf(bit_size) => {
+                    writer.write_bits(0, 1);
+                    writer.write_bits(bit_size as u32, 8);
+                }
+                Node::Branch(zero, one) => {
+                    write_node(nodes, zero, writer);
+                    write_node(nodes, one, writer);
+                    writer.write_bits(1, 1);
+                }
+            }
+        }
+
+        if !self.nodes.is_empty() {
+            write_node(&self.nodes, self.nodes.len() - 1, writer);
+        }
+        writer.write_bits(1, 1);
+    }
+
+    fn read_value(&self, reader: &mut BitReader) -> Result<u32, Crunch64Error> {
+        // A back-reference can't be decoded without its tree
+        let mut node = self.nodes.last().ok_or(Crunch64Error::InvalidVpk0Header)?;
+
+        loop {
+            match *node {
+                Node::Leaf(bit_size) if bit_size as usize > MAX_BIT_SIZE => {
+                    return Err(Crunch64Error::InvalidVpk0Header)
+                }
+                Node::Leaf(bit_size) => return reader.read_bits(bit_size as usize),
+                Node::Branch(zero, one) => {
+                    node = &self.nodes[if reader.read_bit()? { one } else { zero }];
+                }
+            }
+        }
+    }
+
+    // Builds the Huffman tree for the number of values of each bit size.
+    // Ties are broken by bit size, then by creation order, so the result is
+    // deterministic.
+    fn from_frequencies(frequencies: &[usize; MAX_BIT_SIZE + 1]) -> Tree {
+        let mut nodes = Vec::new();
+        let mut heap = BinaryHeap::new();
+
+        for (bit_size, &frequency) in frequencies.iter().enumerate() {
+            if frequency > 0 {
+                nodes.push(Node::Leaf(bit_size as u8));
+                heap.push(Reverse((frequency, nodes.len() - 1)));
+            }
+        }
+
+        while heap.len() > 1 {
+            let Reverse((zero_frequency, zero)) = heap.pop().unwrap();
+            let Reverse((one_frequency, one)) = heap.pop().unwrap();
+            nodes.push(Node::Branch(zero, one));
+            heap.push(Reverse((zero_frequency + one_frequency, nodes.len() - 1)));
+        }
+
+        Tree { nodes }
+    }
+
+    // The code and its length in bits for each bit size that has a leaf
+    fn codes(&self) -> [Option<(u32, usize)>; MAX_BIT_SIZE + 1] {
+        let mut codes = [None; MAX_BIT_SIZE + 1];
+        let mut pending: Vec<(usize, u32, usize)> = self
+            .nodes
+            .len()
+            .checked_sub(1)
+            .map(|root| (root, 0, 0))
+            .into_iter()
+            .collect();
+
+        while let Some((index, code, length)) = pending.pop() {
+            match self.nodes[index] {
+                Node::Leaf(bit_size) => codes[bit_size as usize] = Some((code, length)),
+                Node::Branch(zero, one) => {
+                    pending.push((zero, code << 1, length + 1));
+                    pending.push((one, (code << 1) | 1, length + 1));
+                }
+            }
+        }
+
+        codes
+    }
+}
+
+fn bit_size(value: u32) -> usize {
+    (u32::BITS - value.leading_zeros()) as usize
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data,
+/// which can be followed by unrelated data in `bytes`
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let header = Vpk0Header::parse(bytes)?;
+
+    let mut ret = utils::decompression_buffer(header.uncompressed_size, bytes.len());
+    let compressed_size = decompress_to(bytes, &header, &mut ret)?;
+
+    Ok((ret.into_boxed_slice(), compressed_size))
+}
+
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let header = Vpk0Header::parse(bytes)?;
+
+    let dst = dst
+        .get_mut(..header.uncompressed_size)
+        .ok_or(Crunch64Error::OutOfBounds)?;
+    decompress_to(bytes, &header, &mut utils::SliceOutput::new(dst))?;
+
+    Ok(header.uncompressed_size)
+}
+
+fn decompress_to<O: utils::Output>(
+    bytes: &[u8],
+    header: &Vpk0Header,
+    ret: &mut O,
+) -> Result<usize, Crunch64Error> {
+    let decompressed_size = header.uncompressed_size;
+
+    let mut reader = BitReader::new(&bytes[Vpk0Header::SIZE..]);
+    let offsets = Tree::read(&mut reader)?;
+    let lengths = Tree::read(&mut reader)?;
+
+    while ret.len() < decompressed_size {
+        if !reader.read_bit()? {
+            ret.push(reader.read_bits(8)? as u8)?;
+            continue;
+        }
+
+        let offset = match header.method {
+            Method::OneSample => offsets.read_value(&mut reader)? as u64,
+            Method::TwoSample => {
+                let first = offsets.read_value(&mut reader)? as u64;
+                if first < 3 {
+                    let second = offsets.read_value(&mut reader)? as u64;
+                    (4 * second + first + 1).saturating_sub(8)
+                } else {
+                    4 * first - 8
+                }
+            }
+        };
+        let offset = usize::try_from(offset).map_err(|_| Crunch64Error::InvalidBackReference)?;
+        let length = lengths.read_value(&mut reader)? as usize;
+
+        utils::copy_back_reference(ret, offset, length, decompressed_size)?;
+    }
+
+    Ok(Vpk0Header::SIZE + reader.bytes_read())
+}
+
+// Values stored with the offset tree for a back-reference `offset` bytes back
+fn offset_values(method: Method, offset: u32) -> impl Iterator<Item = u32> {
+    let (first, second) = match method {
+        Method::OneSample => (offset, None),
+        Method::TwoSample if offset % 4 == 0 => (offset / 4 + 2, None),
+        Method::TwoSample => (offset % 4 - 1, Some(offset / 4 + 2)),
+    };
+    iter::once(first).chain(second)
+}
+
+fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
+    // Every byte as a literal takes 9 bits, plus a bit for each empty tree
+    Ok(Vpk0Header::SIZE + (9 * input_size + 2).div_ceil(8))
+}
+
+/// Options for [`compress_with_options`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CompressOptions {
+    pub method: Method,
+}
+
+impl CompressOptions {
+    pub const DEFAULT: CompressOptions = CompressOptions {
+        method: Method::TwoSample,
+    };
+}
+
+impl Default for CompressOptions {
+    fn default() -> CompressOptions {
+        CompressOptions::DEFAULT
+    }
+}
+
+pub fn compress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    compress_with_options(bytes, &CompressOptions::DEFAULT)
+}
+
+/// Compresses `bytes` with Huffman trees built for the back-references that
+/// are found. The output isn't known to match the original encoder, and has
+/// only been checked against this crate's decompressor.
+pub fn compress_with_options(
+    bytes: &[u8],
+    options: &CompressOptions,
+) -> Result<Box<[u8]>, Crunch64Error> {
+    let groups = utils::parse_greedy(bytes, MAX_MATCH_LENGTH, 1);
+    let output = encode(bytes, &groups, options.method);
+
+    // Back-references with unusual sizes can take more bits than the bytes
+    // they replace, so fall back to literals if that makes the output bigger
+    if output.len() > size_for_compressed_buffer(bytes.len())? {
+        let literals = vec![Group::Literal; bytes.len()];
+        return Ok(encode(bytes, &literals, options.method));
+    }
+
+    Ok(output)
+}
+
+fn encode(bytes: &[u8], groups: &[Group], method: Method) -> Box<[u8]> {
+    let mut offset_frequencies = [0; MAX_BIT_SIZE + 1];
+    let mut length_frequencies = [0; MAX_BIT_SIZE + 1];
+
+    for group in groups {
+        if let Group::BackReference { offset, length } = *group {
+            for value in offset_values(method, offset as u32 + 1) {
+                offset_frequencies[bit_size(value)] += 1;
+            }
+            length_frequencies[bit_size(length)] += 1;
+        }
+    }
+
+    let offsets = Tree::from_frequencies(&offset_frequencies);
+    let lengths = Tree::from_frequencies(&length_frequencies);
+    let offset_codes = offsets.codes();
+    let length_codes = lengths.codes();
+
+    // Every value has a leaf of exactly its bit size
+    let write_value = |writer: &mut BitWriter, codes: &[Option<(u32, usize)>], value: u32| {
+        let (code, code_length) = codes[bit_size(value)].unwrap();
+        writer.write_bits(code, code_length);
+        writer.write_bits(value, bit_size(value));
+    };
+
+    let mut header = Vec::with_capacity(bytes.len());
+    Vpk0Header {
+        uncompressed_size: bytes.len(),
+        method,
+    }
+    .write(&mut header);
+
+    let mut writer = BitWriter::new(header);
+    offsets.write(&mut writer);
+    lengths.write(&mut writer);
+
+    let mut input_pos = 0;
+    for group in groups {
+        match *group {
+            Group::Literal => {
+                writer.write_bits(0, 1);
+                writer.write_bits(bytes[input_pos] as u32, 8);
+                input_pos += 1;
+            }
+            Group::BackReference { offset, length } => {
+                writer.write_bits(1, 1);
+                for value in offset_values(method, offset as u32 + 1) {
+                    write_value(&mut writer, &offset_codes, value);
+                }
+                write_value(&mut writer, &length_codes, length);
+                input_pos += length as usize;
+            }
+        }
+    }
+
+    writer.bytes.into_boxed_slice()
+}
+
+/// vpk0 [`Codec`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Vpk0 {
+    pub options: CompressOptions,
+}
+
+impl Vpk0 {
+    pub const DEFAULT: Vpk0 = Vpk0 {
+        options: CompressOptions::DEFAULT,
+    };
+}
+
+impl Codec for Vpk0 {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress_with_options(bytes, &self.options)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Ok(Vpk0Header::parse(bytes)?.uncompressed_size)
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Vpk0Header::parse(bytes)?;
+        Ok(Vpk0Header::SIZE)
+    }
+}
+
+#[cfg(feature = "c_bindings")]
+mod c_bindings {
+    #[no_mangle]
+    pub extern "C" fn crunch64_vpk0_decompress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if src_len < super::Vpk0Header::SIZE {
+            return super::Crunch64Error::OutOfBounds;
+        }
+
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(super::Vpk0Header::SIZE, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::Vpk0Header::parse(bytes) {
+            Err(e) => return e,
+            Ok(header) => unsafe { *dst_size = header.uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_vpk0_decompress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_vpk0_compress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        match super::size_for_compressed_buffer(src_len) {
+            Err(e) => return e,
+            Ok(uncompressed_size) => unsafe { *dst_size = uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_vpk0_compress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+        two_sample: bool,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let options = super::CompressOptions {
+            method: if two_sample {
+                super::Method::TwoSample
+            } else {
+                super::Method::OneSample
+            },
+        };
+        let data = match super::compress_with_options(bytes, &options) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+}
+
+#[cfg(feature = "python_bindings")]
+pub(crate) mod python_bindings {
+    use pyo3::prelude::*;
+    use std::borrow::Cow;
+
+    /**
+     * We use a `Cow` instead of a plain &[u8] because the latter only allows Python's
+     * `bytes` objects, while `Cow`` allows for both `bytes` and `bytearray`.
+     * This is important because an argument typed as `bytes` allows to pass a
+     * `bytearray` object too.
+     */
+
+    #[pyfunction]
+    pub(crate) fn decompress_vpk0(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::decompress(&bytes)?.into()))
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (bytes, two_sample=true))]
+    pub(crate) fn compress_vpk0(
+        bytes: Cow<[u8]>,
+        two_sample: bool,
+    ) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        let options = super::CompressOptions {
+            method: if two_sample {
+                super::Method::TwoSample
+            } else {
+                super::Method::OneSample
+            },
+        };
+        Ok(Cow::Owned(
+            super::compress_with_options(&bytes, &options)?.into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use super::{CompressOptions, Method};
+    use crate::Crunch64Error;
+    use rstest::rstest;
+    use std::{fs, path::PathBuf};
+
+    // The test files were made by this encoder, not taken from real data
+    #[rstest]
+    fn test_decompression_regression(
+        #[files("../test_data/*.vpk0*")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let decompressed = super::decompress(&compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_compression_regression(
+        #[files("../test_data/*.vpk0*")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let method = if path.to_string_lossy().ends_with("-one-sample") {
+            Method::OneSample
+        } else {
+            Method::TwoSample
+        };
+        let compressed =
+            super::compress_with_options(&decompressed_file, &CompressOptions { method })?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_cycle_decompressed(
+        #[files("../test_data/*.vpk0")] path: PathBuf,
+        #[values(Method::OneSample, Method::TwoSample)] method: Method,
+    ) -> Result<(), Crunch64Error> {
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let compressed =
+            super::compress_with_options(&decompressed_file, &CompressOptions { method })?;
+        assert_eq!(
+            super::Vpk0Header::parse(&compressed)?.method,
+            method,
+            "{:?}",
+            path
+        );
+        assert_eq!(decompressed_file, super::decompress(&compressed)?.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_header_cycle(
+        #[files("../test_data/*.vpk0*")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = fs::read(&path).unwrap();
+        let decompressed_file = fs::read(path.with_extension("")).unwrap();
+
+        let header = super::Vpk0Header::parse(&compressed_file)?;
+        assert_eq!(header.uncompressed_size, decompressed_file.len());
+
+        let mut written = Vec::new();
+        header.write(&mut written);
+        assert_eq!(written, &compressed_file[..super::Vpk0Header::SIZE]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_one_sample() -> Result<(), Crunch64Error> {
+        // Offset tree with a single 2-bit leaf, length tree with a single
+        // 3-bit leaf, the literals "abc" and a back-reference of 6 bytes
+        // starting 3 bytes back
+        let mut bytes = b"vpk0\x00\x00\x00\x09\x00".to_vec();
+        bytes.extend([0x01, 0x40, 0x73, 0x09, 0x88, 0xC7, 0xF0]);
+
+        assert_eq!(super::decompress(&bytes)?.as_ref(), b"abcabcabc");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_two_sample() -> Result<(), Crunch64Error> {
+        // Offset tree with a single 2-bit leaf, length tree with 2-bit and
+        // 3-bit leaves, the literals "abcd", a back-reference of 4 bytes
+        // starting 4 bytes back (stored as 3) and one of 3 bytes starting 3
+        // bytes back (stored as 2 for the remainder and 2 for the quotient)
+        let mut bytes = b"vpk0\x00\x00\x00\x0B\x01".to_vec();
+        bytes.extend([
+            0x01, 0x40, 0x40, 0x3C, 0xC2, 0x62, 0x31, 0x99, 0x3E, 0x69, 0x80,
+        ]);
+
+        assert_eq!(super::decompress(&bytes)?.as_ref(), b"abcdabcdbcd");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_errors() {
+        assert_eq!(
+            super::decompress(b"vpk1\x00\x00\x00\x01\x00\x80"),
+            Err(Crunch64Error::InvalidVpk0Header)
+        );
+        assert_eq!(
+            super::decompress(b"vpk0\x00\x00\x00\x01\x02\x80"),
+            Err(Crunch64Error::InvalidVpk0Header)
+        );
+
+        // Empty trees followed by a back-reference
+        assert_eq!(
+            super::decompress(b"vpk0\x00\x00\x00\x01\x00\xE0"),
+            Err(Crunch64Error::InvalidVpk0Header)
+        );
+
+        // Empty trees followed by nothing
+        assert_eq!(
+            super::decompress(b"vpk0\x00\x00\x00\x01\x00\xC0"),
+            Err(Crunch64Error::TruncatedInput)
+        );
+    }
+
+    #[test]
+    fn test_incompressible_input() -> Result<(), Crunch64Error> {
+        let data: Vec<u8> = (0..=255).collect();
+
+        let compressed = super::compress(&data)?;
+        assert!(compressed.len() <= super::size_for_compressed_buffer(data.len())?);
+        assert_eq!(super::decompress(&compressed)?.as_ref(), data);
+        Ok(())
+    }
+}
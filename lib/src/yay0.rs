@@ -1,52 +1,94 @@
 use alloc::{boxed::Box, vec::Vec};
 
-use crate::{utils, Crunch64Error};
+use crate::{
+    utils::{self, Group},
+    Codec, Crunch64Error,
+};
 
-fn parse_header(bytes: &[u8]) -> Result<(usize, usize, usize), Crunch64Error> {
-    if bytes.len() < 0x10 {
-        return Err(Crunch64Error::InvalidYay0Header);
+#[cfg(feature = "std")]
+use crate::stream;
+#[cfg(feature = "std")]
+use std::io;
+
+/// Fields of the 0x10-byte Yay0 header
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Yay0Header {
+    pub uncompressed_size: usize,
+    /// Offset of the link table (back-references) from the start of the data
+    pub link_table_offset: usize,
+    /// Offset of the chunk (literal bytes) data from the start of the data
+    pub chunk_offset: usize,
+}
+
+impl Yay0Header {
+    pub const SIZE: usize = 0x10;
+
+    pub fn parse(bytes: &[u8]) -> Result<Yay0Header, Crunch64Error> {
+        if bytes.len() < Self::SIZE {
+            return Err(Crunch64Error::InvalidYay0Header);
+        }
+
+        if &bytes[0..4] != b"Yay0" {
+            return Err(Crunch64Error::InvalidYay0Header);
+        }
+
+        Ok(Yay0Header {
+            uncompressed_size: utils::read_u32(bytes, 0x4)? as usize,
+            link_table_offset: utils::read_u32(bytes, 0x8)? as usize,
+            chunk_offset: utils::read_u32(bytes, 0xC)? as usize,
+        })
     }
 
-    if &bytes[0..4] != b"Yay0" {
-        return Err(Crunch64Error::InvalidYay0Header);
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        dst.extend(b"Yay0");
+        dst.extend((self.uncompressed_size as u32).to_be_bytes());
+        dst.extend((self.link_table_offset as u32).to_be_bytes());
+        dst.extend((self.chunk_offset as u32).to_be_bytes());
     }
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data,
+/// which can be followed by unrelated data in `bytes`
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let header = Yay0Header::parse(bytes)?;
 
-    let decompressed_size = utils::read_u32(bytes, 0x4)? as usize;
-    let link_table_offset = utils::read_u32(bytes, 0x8)? as usize;
-    let chunk_offset = utils::read_u32(bytes, 0xC)? as usize;
+    let mut ret = utils::decompression_buffer(header.uncompressed_size, bytes.len());
+    let compressed_size = decompress_to(bytes, &header, &mut ret)?;
 
-    Ok((decompressed_size, link_table_offset, chunk_offset))
+    Ok((ret.into_boxed_slice(), compressed_size))
 }
 
-fn write_header(
-    dst: &mut Vec<u8>,
-    uncompressed_size: usize,
-    link_table_offset: usize,
-    chunk_offset: usize,
-) -> Result<(), Crunch64Error> {
-    dst.extend(b"Yay0");
-    dst.extend((uncompressed_size as u32).to_be_bytes());
-    dst.extend((link_table_offset as u32).to_be_bytes());
-    dst.extend((chunk_offset as u32).to_be_bytes());
-
-    Ok(())
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let header = Yay0Header::parse(bytes)?;
+
+    let dst = dst
+        .get_mut(..header.uncompressed_size)
+        .ok_or(Crunch64Error::OutOfBounds)?;
+    decompress_to(bytes, &header, &mut utils::SliceOutput::new(dst))?;
+
+    Ok(header.uncompressed_size)
 }
 
-pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
-    let (decompressed_size, link_table_offset, chunk_offset) = parse_header(bytes)?;
+fn decompress_to<O: utils::Output>(
+    bytes: &[u8],
+    header: &Yay0Header,
+    ret: &mut O,
+) -> Result<usize, Crunch64Error> {
+    let decompressed_size = header.uncompressed_size;
 
-    let mut link_table_idx = link_table_offset;
-    let mut chunk_idx = chunk_offset;
-    let mut other_idx = 0x10;
+    let mut link_table_idx = header.link_table_offset;
+    let mut chunk_idx = header.chunk_offset;
+    let mut other_idx = Yay0Header::SIZE;
 
     let mut mask_bit_counter = 0;
     let mut current_mask = 0;
 
-    // Preallocate result and index into it
-    let mut idx: usize = 0;
-    let mut ret: Vec<u8> = vec![0u8; decompressed_size];
-
-    while idx < decompressed_size {
+    while ret.len() < decompressed_size {
         // If we're out of bits, get the next mask
         if mask_bit_counter == 0 {
             current_mask = utils::read_u32(bytes, other_idx)?;
@@ -55,140 +97,343 @@ pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
         }
 
         if current_mask & 0x80000000 != 0 {
-            ret[idx] = bytes[chunk_idx];
-            idx += 1;
-            chunk_idx += 1;
+            ret.push(utils::next_u8(bytes, &mut chunk_idx)?)?;
         } else {
             let link = utils::read_u16(bytes, link_table_idx)? as usize;
             link_table_idx += 2;
 
-            let offset = idx - (link & 0xFFF);
+            let offset = (link & 0xFFF) + 1;
 
-            let mut count = (link >> 12) as usize;
+            let mut count = link >> 12;
 
             if count == 0 {
-                let count_modifier = bytes[chunk_idx];
-                chunk_idx += 1;
+                let count_modifier = utils::next_u8(bytes, &mut chunk_idx)?;
                 count = count_modifier as usize + 18;
             } else {
                 count += 2;
             }
 
-            for i in 0..count {
-                ret[idx] = ret[offset + i - 1];
-                idx += 1;
-            }
+            utils::copy_back_reference(ret, offset, count, decompressed_size)?;
         }
 
         current_mask <<= 1;
         mask_bit_counter -= 1;
     }
 
-    Ok(ret.into_boxed_slice())
+    // The link table and the chunks usually come last, but the three parts
+    // could be in any order
+    Ok(other_idx.max(link_table_idx).max(chunk_idx))
 }
 
 fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
-    // Taken from Yaz0
-    Ok(input_size + input_size.div_ceil(8) + 0x10)
+    // Every byte as a chunk, plus a 4-byte mask word for every 32 of them
+    Ok(0x10 + 4 * input_size.div_ceil(32) + input_size)
+}
+
+/// Options for [`compress_with_options`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CompressOptions {
+    /// Find the smallest possible compressed data instead of matching the
+    /// original encoder
+    pub optimal: bool,
+}
+
+impl CompressOptions {
+    pub const DEFAULT: CompressOptions = CompressOptions { optimal: false };
+}
+
+impl Default for CompressOptions {
+    fn default() -> CompressOptions {
+        CompressOptions::DEFAULT
+    }
 }
 
 pub fn compress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    compress_with_options(bytes, &CompressOptions::DEFAULT)
+}
+
+pub fn compress_with_options(
+    bytes: &[u8],
+    options: &CompressOptions,
+) -> Result<Box<[u8]>, Crunch64Error> {
     let input_size = bytes.len();
 
-    let mut window = utils::Window::new(bytes);
+    let groups = if options.optimal {
+        utils::parse_optimal(bytes, 0x111, match_cost)
+    } else {
+        utils::parse_greedy(bytes, 0x111, 1)
+    };
 
-    let mut pp: usize = 0;
-    let mut index_cur_layout_byte: usize = 0;
+    let mut tables = utils::Tables::default();
+    let mut input_pos: usize = 0;
 
-    let mut cmd: Vec<u32> = vec![0; 0x4000];
-    let mut pol: Vec<u16> = Vec::with_capacity(2 * 0x1000);
-    let mut def: Vec<u8> = Vec::with_capacity(4 * 0x1000);
+    for group in groups {
+        push_group(&mut tables, group, bytes[input_pos]);
 
-    let mut input_pos: usize = 0;
-    let mut cur_layout_bit: u32 = 0x80000000;
+        // Move forward in the input by the size of the group
+        input_pos += group.length();
+    }
 
-    while input_pos < input_size {
-        let (mut group_pos, mut group_size) = window.search(input_pos, 0x111);
+    let mut output: Vec<u8> = Vec::with_capacity(size_for_compressed_buffer(input_size)?);
 
-        // If the group isn't larger than 2 bytes, copying the input without compression is smaller
-        if group_size <= 2 {
-            // Set the current layout bit to indicate that this is an uncompressed byte
-            cmd[index_cur_layout_byte] |= cur_layout_bit;
-            def.push(bytes[input_pos]);
-            input_pos += 1;
-        } else {
-            // Search for a new group after one position after the current one
-            let (new_position, new_size) = window.search(input_pos + 1, 0x111);
-
-            // If the new group is better than the current group by at least 2 bytes, use it instead
-            if new_size >= group_size + 2 {
-                // Mark the current layout bit to skip compressing this byte, as the next input position yielded better compression
-                cmd[index_cur_layout_byte] |= cur_layout_bit;
-                def.push(bytes[input_pos]);
-                input_pos += 1;
-
-                // Advance to the next layout bit
-                cur_layout_bit >>= 1;
-
-                if cur_layout_bit == 0 {
-                    cur_layout_bit = 0x80000000;
-                    index_cur_layout_byte += 1;
-                    cmd[index_cur_layout_byte] = 0;
-                }
-
-                group_size = new_size;
-                group_pos = new_position;
-            }
+    header_for(&tables, input_size).write(&mut output);
+    output.extend(&tables.layout);
+    output.extend(&tables.links);
+    output.extend(&tables.chunks);
 
-            // Calculate the offset for the current group
-            let group_offset = input_pos - group_pos as usize - 1;
+    Ok(output.into_boxed_slice())
+}
 
+// Adds `group` to the tables. `literal` is the input byte the group starts with.
+fn push_group(tables: &mut utils::Tables, group: Group, literal: u8) {
+    match group {
+        // Set the current layout bit to indicate that this is an uncompressed byte
+        Group::Literal => tables.push_literal(literal),
+        Group::BackReference { offset, length } => {
             // Determine which encoding to use for the current group
-            if group_size >= 0x12 {
-                pol.push(group_offset as u16);
-                pp += 1;
-                def.push((group_size - 0x12) as u8);
+            if length >= 0x12 {
+                tables.push_link(offset);
+                tables.chunks.push((length - 0x12) as u8);
             } else {
-                pol.push((group_offset | (((group_size as u16 - 2) as usize) << 12)) as u16);
-                pp += 1;
+                tables.push_link(offset | ((length as u16 - 2) << 12));
             }
-
-            // Move forward in the input by the size of the group
-            input_pos += group_size as usize;
         }
+    }
+}
 
-        // Advance to the next layout bit
-        cur_layout_bit >>= 1;
+// Header for the data in `tables`
+fn header_for(tables: &utils::Tables, uncompressed_size: usize) -> Yay0Header {
+    Yay0Header {
+        uncompressed_size,
+        link_table_offset: tables.link_table_offset(),
+        chunk_offset: tables.chunk_offset(),
+    }
+}
 
-        if cur_layout_bit == 0 {
-            cur_layout_bit = 0x80000000;
-            index_cur_layout_byte += 1;
-            cmd[index_cur_layout_byte] = 0;
+// Size in bits of a back-reference, including its layout bit
+fn match_cost(length: usize) -> usize {
+    if length >= 0x12 {
+        25
+    } else {
+        17
+    }
+}
+
+/// Yay0 [`Codec`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Yay0 {
+    pub options: CompressOptions,
+}
+
+impl Yay0 {
+    pub const DEFAULT: Yay0 = Yay0 {
+        options: CompressOptions::DEFAULT,
+    };
+}
+
+impl Codec for Yay0 {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress_with_options(bytes, &self.options)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Ok(Yay0Header::parse(bytes)?.uncompressed_size)
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Yay0Header::parse(bytes)?;
+        Ok(Yay0Header::SIZE)
+    }
+}
+
+/// Decompresses Yay0 data from a [`Read`](io::Read) stream, producing the same
+/// bytes as [`decompress`] without holding all of them in memory.
+///
+/// Everything before the chunk data (the header, layout bits and link table)
+/// is buffered, while the chunks are read one byte at a time and never past
+/// their end. Unbuffered readers should be wrapped in a
+/// [`BufReader`](io::BufReader).
+#[cfg(feature = "std")]
+pub struct Yay0Decoder<R: io::Read> {
+    inner: R,
+    header: Yay0Header,
+    tables: Vec<u8>,
+    history: stream::History,
+    layout_idx: usize,
+    link_table_idx: usize,
+    current_mask: u32,
+    mask_bit_counter: u32,
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> Yay0Decoder<R> {
+    /// Reads the header, layout bits and link table from `inner`
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut tables = stream::read_up_to(&mut inner, Yay0Header::SIZE)?;
+        let header = Yay0Header::parse(&tables)?;
+
+        // The chunks are read straight from `inner`, so they can't overlap the header
+        if header.chunk_offset < Yay0Header::SIZE {
+            return Err(Crunch64Error::InvalidYay0Header.into());
         }
+        tables.extend(stream::read_up_to(
+            &mut inner,
+            header.chunk_offset - Yay0Header::SIZE,
+        )?);
+
+        Ok(Yay0Decoder {
+            inner,
+            header,
+            tables,
+            history: stream::History::new(header.uncompressed_size),
+            layout_idx: Yay0Header::SIZE,
+            link_table_idx: header.link_table_offset,
+            current_mask: 0,
+            mask_bit_counter: 0,
+        })
     }
 
-    if cur_layout_bit != 0x80000000 {
-        index_cur_layout_byte += 1;
+    pub fn header(&self) -> &Yay0Header {
+        &self.header
     }
 
-    let link_table_offset: usize = 4 * index_cur_layout_byte + 16;
-    let chunk_offset: usize = 2 * pp + link_table_offset;
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
 
-    let mut output: Vec<u8> = Vec::with_capacity(size_for_compressed_buffer(input_size)?);
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> io::Read for Yay0Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        stream::decode_into(&mut self.history, buf, |history| {
+            // If we're out of bits, get the next mask
+            if self.mask_bit_counter == 0 {
+                self.current_mask = utils::read_u32(&self.tables, self.layout_idx)?;
+                self.layout_idx += 4;
+                self.mask_bit_counter = 32;
+            }
+
+            let is_literal = self.current_mask & 0x80000000 != 0;
+            self.current_mask <<= 1;
+            self.mask_bit_counter -= 1;
+
+            if is_literal {
+                return Ok(Some(stream::read_u8(&mut self.inner)?));
+            }
+
+            let link = utils::read_u16(&self.tables, self.link_table_idx)? as usize;
+            self.link_table_idx += 2;
+
+            let offset = (link & 0xFFF) + 1;
+
+            let mut count = link >> 12;
+
+            if count == 0 {
+                count = stream::read_u8(&mut self.inner)? as usize + 18;
+            } else {
+                count += 2;
+            }
+
+            history.start_copy(offset, count)?;
+            Ok(None)
+        })
+    }
+}
+
+/// Compresses the data written to it as Yay0, producing the same bytes as
+/// [`compress`] without holding all of the input in memory.
+///
+/// The header needs the size of every table, so the layout bits, link table
+/// and chunks are kept separately and written by [`Yay0Encoder::finish`].
+/// Dropping the encoder finishes it too, but any error is lost.
+#[cfg(feature = "std")]
+pub struct Yay0Encoder<W: io::Write> {
+    inner: Option<W>,
+    parser: stream::GreedyStream,
+    uncompressed_size: usize,
+    tables: utils::Tables,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> Yay0Encoder<W> {
+    pub fn new(inner: W) -> Self {
+        Yay0Encoder {
+            inner: Some(inner),
+            parser: stream::GreedyStream::new(0x111, 1),
+            uncompressed_size: 0,
+            tables: utils::Tables::default(),
+        }
+    }
 
-    write_header(&mut output, input_size, link_table_offset, chunk_offset)?;
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
 
-    for &value in &cmd[..index_cur_layout_byte] {
-        output.extend(&value.to_be_bytes());
+    /// Compresses the rest of the data, writes everything and returns the
+    /// inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()
     }
 
-    for &value in &pol[..pp] {
-        output.extend(&value.to_be_bytes());
+    fn try_finish(&mut self) -> io::Result<W> {
+        let mut inner = self.inner.take().unwrap();
+        self.parser
+            .finish(|group, literal| push_group(&mut self.tables, group, literal));
+
+        let mut header = Vec::with_capacity(Yay0Header::SIZE);
+        header_for(&self.tables, self.uncompressed_size).write(&mut header);
+        inner.write_all(&header)?;
+        inner.write_all(&self.tables.layout)?;
+        inner.write_all(&self.tables.links)?;
+        inner.write_all(&self.tables.chunks)?;
+        inner.flush()?;
+        Ok(inner)
     }
+}
 
-    output.extend(&def);
+#[cfg(feature = "std")]
+impl<W: io::Write> io::Write for Yay0Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.uncompressed_size += buf.len();
+        self.parser.push(buf, |group, literal| {
+            push_group(&mut self.tables, group, literal)
+        });
+        Ok(buf.len())
+    }
 
-    Ok(output.into_boxed_slice())
+    fn flush(&mut self) -> io::Result<()> {
+        // Nothing can be written before the sizes of the tables are known
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> Drop for Yay0Encoder<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.try_finish();
+        }
+    }
 }
 
 #[cfg(feature = "c_bindings")]
@@ -207,14 +452,14 @@ mod c_bindings {
             return super::Crunch64Error::NullPointer;
         }
 
-        let bytes = match super::utils::u8_vec_from_pointer_array(0x10, src) {
+        let bytes = match super::utils::u8_slice_from_pointer_array(0x10, src) {
             Err(e) => return e,
             Ok(d) => d,
         };
 
-        match super::parse_header(&bytes) {
+        match super::Yay0Header::parse(bytes) {
             Err(e) => return e,
-            Ok((value, _, _)) => unsafe { *dst_size = value },
+            Ok(header) => unsafe { *dst_size = header.uncompressed_size },
         }
 
         super::Crunch64Error::Okay
@@ -231,18 +476,19 @@ mod c_bindings {
             return super::Crunch64Error::NullPointer;
         }
 
-        let bytes = match super::utils::u8_vec_from_pointer_array(src_len, src) {
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
             Err(e) => return e,
             Ok(d) => d,
         };
 
-        let data = match super::decompress(&bytes) {
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
             Err(e) => return e,
             Ok(d) => d,
         };
 
-        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
-            return e;
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
         }
 
         super::Crunch64Error::Okay
@@ -277,12 +523,41 @@ mod c_bindings {
             return super::Crunch64Error::NullPointer;
         }
 
-        let bytes = match super::utils::u8_vec_from_pointer_array(src_len, src) {
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
             Err(e) => return e,
             Ok(d) => d,
         };
 
-        let data = match super::compress(&bytes) {
+        let data = match super::compress(bytes) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_yay0_compress_optimal(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let options = super::CompressOptions { optimal: true };
+        let data = match super::compress_with_options(bytes, &options) {
             Err(e) => return e,
             Ok(d) => d,
         };
@@ -313,8 +588,15 @@ pub(crate) mod python_bindings {
     }
 
     #[pyfunction]
-    pub(crate) fn compress_yay0(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
-        Ok(Cow::Owned(super::compress(&bytes)?.into()))
+    #[pyo3(signature = (bytes, optimal=false))]
+    pub(crate) fn compress_yay0(
+        bytes: Cow<[u8]>,
+        optimal: bool,
+    ) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        let options = super::CompressOptions { optimal };
+        Ok(Cow::Owned(
+            super::compress_with_options(&bytes, &options)?.into(),
+        ))
     }
 }
 
@@ -326,7 +608,7 @@ mod tests {
     use rstest::rstest;
     use std::{
         fs::File,
-        io::{BufReader, Read},
+        io::{self, BufReader, Read, Write},
         path::PathBuf,
     };
 
@@ -395,4 +677,109 @@ mod tests {
         );
         Ok(())
     }
+
+    #[rstest]
+    fn test_optimal_compression(
+        #[files("../test_data/*.Yay0")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let options = super::CompressOptions { optimal: true };
+        let compressed = super::compress_with_options(decompressed_file, &options)?;
+        // Never larger than the output of the original encoder
+        assert!(compressed.len() <= compressed_file.len());
+        assert_eq!(decompressed_file, super::decompress(&compressed)?.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_header_cycle(
+        #[files("../test_data/*.Yay0")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let header = super::Yay0Header::parse(compressed_file)?;
+        assert_eq!(header.uncompressed_size, decompressed_file.len());
+
+        let mut written = Vec::new();
+        header.write(&mut written);
+        assert_eq!(written, &compressed_file[..super::Yay0Header::SIZE]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_streaming(#[files("../test_data/*.Yay0")] path: PathBuf) -> io::Result<()> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        // Read in small pieces to stop in the middle of back-references
+        let mut decoder = super::Yay0Decoder::new(compressed_file.as_slice())?;
+        let mut decompressed: Vec<u8> = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let read = decoder.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            decompressed.extend(&buf[..read]);
+        }
+        assert_eq!(decompressed_file, &decompressed);
+        assert!(decoder.into_inner().is_empty());
+
+        let mut encoder = super::Yay0Encoder::new(Vec::new());
+        for piece in decompressed_file.chunks(0x1234) {
+            encoder.write_all(piece)?;
+        }
+        assert_eq!(compressed_file, &encoder.finish()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_errors() {
+        // Layout bits for a single back-reference, which points before the first byte
+        let mut bytes = b"Yay0".to_vec();
+        bytes.extend(8u32.to_be_bytes());
+        bytes.extend(0x14u32.to_be_bytes());
+        bytes.extend(0x16u32.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+        bytes.extend(0x1000u16.to_be_bytes());
+        assert_eq!(
+            super::decompress(&bytes),
+            Err(Crunch64Error::InvalidBackReference)
+        );
+
+        // Missing link table
+        assert_eq!(
+            super::decompress(&bytes[..0x14]),
+            Err(Crunch64Error::TruncatedInput)
+        );
+    }
+
+    #[rstest]
+    fn test_large_input(
+        #[values(0x100000, 0x400000)] size: usize,
+        #[values(false, true)] zeros: bool,
+    ) -> Result<(), Crunch64Error> {
+        // Bigger than the 0x4000 words of layout bits that used to be preallocated
+        let data = if zeros {
+            vec![0; size]
+        } else {
+            crate::utils::tests::synthetic_data(size)
+        };
+
+        let compressed = super::compress(&data)?;
+        let header = super::Yay0Header::parse(&compressed)?;
+        assert_eq!(header.uncompressed_size, size);
+        assert!(header.link_table_offset <= header.chunk_offset);
+
+        assert_eq!(super::decompress(&compressed)?.as_ref(), data);
+
+        // The streaming encoder parses the input in pieces
+        let mut encoder = super::Yay0Encoder::new(Vec::new());
+        encoder.write_all(&data).unwrap();
+        assert_eq!(encoder.finish().unwrap(), compressed.as_ref());
+        Ok(())
+    }
 }
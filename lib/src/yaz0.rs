@@ -2,86 +2,164 @@
 
 use alloc::{boxed::Box, vec::Vec};
 
-use crate::{utils, Crunch64Error};
+use crate::{
+    utils::{self, Group},
+    Codec, Crunch64Error,
+};
 
-fn parse_header(bytes: &[u8]) -> Result<usize, Crunch64Error> {
-    if bytes.len() < 0x10 {
-        return Err(Crunch64Error::InvalidYaz0Header);
+#[cfg(feature = "std")]
+use crate::stream;
+#[cfg(feature = "std")]
+use std::io;
+
+/// Fields of the 0x10-byte Yaz0 header
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Yaz0Header {
+    pub uncompressed_size: usize,
+    /// Data alignment stored at offset 8 by later Nintendo tools, or 0
+    pub alignment: u32,
+}
+
+impl Yaz0Header {
+    pub const SIZE: usize = 0x10;
+
+    /// Parses a classic Yaz0 header, where everything after the uncompressed
+    /// size must be zero
+    pub fn parse(bytes: &[u8]) -> Result<Yaz0Header, Crunch64Error> {
+        let header = Self::parse_lenient(bytes)?;
+
+        if bytes[8..0x10] != [0u8; 8] {
+            return Err(Crunch64Error::InvalidYaz0Header);
+        }
+
+        Ok(header)
     }
 
-    if &bytes[0..4] != b"Yaz0" {
-        return Err(Crunch64Error::InvalidYaz0Header);
+    /// Parses a Yaz0 header, reading the alignment field and ignoring the
+    /// rest of the padding
+    pub fn parse_lenient(bytes: &[u8]) -> Result<Yaz0Header, Crunch64Error> {
+        if bytes.len() < Self::SIZE {
+            return Err(Crunch64Error::InvalidYaz0Header);
+        }
+
+        if &bytes[0..4] != b"Yaz0" {
+            return Err(Crunch64Error::InvalidYaz0Header);
+        }
+
+        Ok(Yaz0Header {
+            uncompressed_size: utils::read_u32(bytes, 4)? as usize,
+            alignment: utils::read_u32(bytes, 8)?,
+        })
     }
 
-    if bytes[8..0x10] != [0u8; 8] {
-        return Err(Crunch64Error::InvalidYaz0Header);
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        dst.extend(b"Yaz0");
+        dst.extend((self.uncompressed_size as u32).to_be_bytes());
+        dst.extend(self.alignment.to_be_bytes());
+        // padding
+        dst.extend(&[0u8; 4]);
     }
+}
 
-    Ok(utils::read_u32(bytes, 4)? as usize)
+/// Options for [`compress_with_options`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CompressOptions {
+    /// Data alignment to store in the header. It doesn't change the
+    /// compressed data.
+    pub alignment: u32,
+    /// Find the smallest possible compressed data instead of matching the
+    /// original encoder
+    pub optimal: bool,
 }
 
-fn write_header(dst: &mut Vec<u8>, uncompressed_size: usize) -> Result<(), Crunch64Error> {
-    dst.extend(b"Yaz0");
-    dst.extend((uncompressed_size as u32).to_be_bytes());
-    // padding
-    dst.extend(&[0u8; 8]);
+impl CompressOptions {
+    pub const DEFAULT: CompressOptions = CompressOptions {
+        alignment: 0,
+        optimal: false,
+    };
+}
 
-    Ok(())
+impl Default for CompressOptions {
+    fn default() -> CompressOptions {
+        CompressOptions::DEFAULT
+    }
 }
 
+/// Decompresses Yaz0 data. The header is read with
+/// [`Yaz0Header::parse_lenient`], since files from later Nintendo tools store
+/// an alignment there that doesn't change the data. Use [`Yaz0Header::parse`]
+/// first to only accept classic headers.
 pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
-    let uncompressed_size = parse_header(bytes)?;
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data,
+/// which can be followed by unrelated data in `bytes`
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let header = Yaz0Header::parse_lenient(bytes)?;
+
+    let mut ret = utils::decompression_buffer(header.uncompressed_size, bytes.len());
+    let compressed_size = decompress_to(bytes, &header, &mut ret)?;
+
+    Ok((ret.into_boxed_slice(), compressed_size))
+}
+
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let header = Yaz0Header::parse_lenient(bytes)?;
+
+    let dst = dst
+        .get_mut(..header.uncompressed_size)
+        .ok_or(Crunch64Error::OutOfBounds)?;
+    decompress_to(bytes, &header, &mut utils::SliceOutput::new(dst))?;
+
+    Ok(header.uncompressed_size)
+}
+
+fn decompress_to<O: utils::Output>(
+    bytes: &[u8],
+    header: &Yaz0Header,
+    ret: &mut O,
+) -> Result<usize, Crunch64Error> {
+    let uncompressed_size = header.uncompressed_size;
 
     // Skip the header
-    let mut index_src = 0x10;
-    let mut index_dst = 0;
+    let mut index_src = Yaz0Header::SIZE;
 
-    let mut ret = vec![0u8; uncompressed_size];
+    while ret.len() < uncompressed_size {
+        let mut layout_bits = utils::next_u8(bytes, &mut index_src)?;
 
-    while index_src < bytes.len() {
-        let mut layout_bit_index = 0;
-        let mut layout_bits = bytes[index_src];
-        index_src += 1;
+        for _ in 0..8 {
+            if ret.len() >= uncompressed_size {
+                break;
+            }
 
-        while (layout_bit_index < 8) && (index_src < bytes.len()) && (index_dst < uncompressed_size)
-        {
             if (layout_bits & 0x80) != 0 {
-                ret[index_dst] = bytes[index_src];
-                index_src += 1;
-                index_dst += 1;
+                ret.push(utils::next_u8(bytes, &mut index_src)?)?;
             } else {
-                let first_byte = bytes[index_src];
-                index_src += 1;
-                let second_byte = bytes[index_src];
-                index_src += 1;
+                let first_byte = utils::next_u8(bytes, &mut index_src)?;
+                let second_byte = utils::next_u8(bytes, &mut index_src)?;
                 let byte_pair = ((first_byte as u16) << 8) | (second_byte as u16);
                 let offset = (byte_pair & 0x0FFF) + 1;
-                let mut length: usize;
 
                 // Check how the group length is encoded
-                if (first_byte & 0xF0) == 0 {
+                let length = if (first_byte & 0xF0) == 0 {
                     // 3 byte encoding, 0RRRNN
-                    let third_byte = bytes[index_src];
-                    index_src += 1;
-                    length = (third_byte as usize) + 0x12;
+                    let third_byte = utils::next_u8(bytes, &mut index_src)?;
+                    (third_byte as usize) + 0x12
                 } else {
                     // 2 byte encoding, NRRR
-                    length = (((byte_pair & 0xF000) >> 12) + 2) as usize;
-                }
+                    (((byte_pair & 0xF000) >> 12) + 2) as usize
+                };
 
-                while length > 0 {
-                    ret[index_dst] = ret[index_dst - offset as usize];
-                    index_dst += 1;
-                    length -= 1;
-                }
+                utils::copy_back_reference(ret, offset as usize, length, uncompressed_size)?;
             }
 
-            layout_bit_index += 1;
             layout_bits <<= 1;
         }
     }
 
-    Ok(ret.into_boxed_slice())
+    Ok(index_src)
 }
 
 fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
@@ -92,91 +170,334 @@ fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error>
 }
 
 pub fn compress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    compress_with_options(bytes, &CompressOptions::DEFAULT)
+}
+
+pub fn compress_with_options(
+    bytes: &[u8],
+    options: &CompressOptions,
+) -> Result<Box<[u8]>, Crunch64Error> {
     let input_size = bytes.len();
 
+    let groups = if options.optimal {
+        utils::parse_optimal(bytes, 0x111, match_cost)
+    } else {
+        utils::parse_greedy(bytes, 0x111, 1)
+    };
+
     let mut output: Vec<u8> = Vec::with_capacity(size_for_compressed_buffer(input_size)?);
-    let mut window = utils::Window::new(bytes);
 
-    write_header(&mut output, input_size)?;
+    Yaz0Header {
+        uncompressed_size: input_size,
+        alignment: options.alignment,
+    }
+    .write(&mut output);
 
-    let mut index_cur_layout_byte: usize = 0x10;
-    let mut index_out_ptr: usize = index_cur_layout_byte;
+    let mut writer = GroupWriter::default();
     let mut input_pos: usize = 0;
-    let mut cur_layout_bit: u8 = 1;
 
-    while input_pos < input_size {
-        // Advance to the next layout bit
-        cur_layout_bit >>= 1;
+    for group in groups {
+        writer.write(&mut output, group, bytes[input_pos]);
+
+        // Move forward in the input by the size of the group
+        input_pos += group.length();
+    }
+
+    Ok(output.into_boxed_slice())
+}
+
+// Writes groups as Yaz0 data, with a layout byte before every 8 of them
+#[derive(Copy, Clone, Debug, Default)]
+struct GroupWriter {
+    // Position of the current layout byte in the output
+    layout_index: usize,
+    group_count: usize,
+}
+
+impl GroupWriter {
+    // Appends `group` to `output`. `literal` is the input byte the group starts with.
+    fn write(&mut self, output: &mut Vec<u8>, group: Group, literal: u8) {
+        let bit = self.group_count % 8;
+        self.group_count += 1;
 
-        if cur_layout_bit == 0 {
-            cur_layout_bit = 0x80;
-            index_cur_layout_byte = index_out_ptr;
+        // Each layout byte comes before the 8 groups it describes
+        if bit == 0 {
+            self.layout_index = output.len();
             output.push(0);
-            index_out_ptr += 1;
         }
 
-        let (mut group_pos, mut group_size) = window.search(input_pos, 0x111);
+        match group {
+            Group::Literal => {
+                // Set the current layout bit to indicate that this is an uncompressed byte
+                output[self.layout_index] |= 0x80 >> bit;
+                output.push(literal);
+            }
+            Group::BackReference { offset, length } => {
+                // Determine which encoding to use for the current group
+                if length >= 0x12 {
+                    // Three bytes, 0RRRNN
+                    output.push((offset >> 8) as u8);
+                    output.push((offset & 0xFF) as u8);
+                    output.push((length - 0x12) as u8);
+                } else {
+                    // Two bytes, NRRR
+                    output.push((offset >> 8) as u8 | ((length - 2) << 4) as u8);
+                    output.push((offset & 0xFF) as u8);
+                }
+            }
+        }
+    }
 
-        // If the group isn't larger than 2 bytes, copying the input without compression is smaller
-        if group_size <= 2 {
-            // Set the current layout bit to indicate that this is an uncompressed byte
-            output[index_cur_layout_byte] |= cur_layout_bit;
-            output.push(bytes[input_pos]);
-            input_pos += 1;
-            index_out_ptr += 1;
+    // Number of bytes at the start of `output` that no later group can change
+    #[cfg(feature = "std")]
+    fn complete_length(&self, output: &[u8]) -> usize {
+        if self.group_count % 8 == 0 {
+            output.len()
         } else {
-            // Search for a new group after one position after the current one
-            let (new_position, new_size) = window.search(input_pos + 1, 0x111);
-
-            // If the new group is better than the current group by at least 2 bytes, use it instead
-            if new_size >= group_size + 2 {
-                // Mark the current layout bit to skip compressing this byte, as the next input position yielded better compression
-                output[index_cur_layout_byte] |= cur_layout_bit;
-                // Copy the input byte to the output
-                output.push(bytes[input_pos]);
-                input_pos += 1;
-                index_out_ptr += 1;
-
-                // Advance to the next layout bit
-                cur_layout_bit >>= 1;
-
-                if cur_layout_bit == 0 {
-                    cur_layout_bit = 0x80;
-                    index_cur_layout_byte = index_out_ptr;
-                    output.push(0);
-                    index_out_ptr += 1;
-                }
+            self.layout_index
+        }
+    }
+}
+
+// Size in bits of a back-reference, including its layout bit
+fn match_cost(length: usize) -> usize {
+    if length >= 0x12 {
+        25
+    } else {
+        17
+    }
+}
+
+/// Yaz0 [`Codec`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Yaz0 {
+    pub options: CompressOptions,
+}
+
+impl Yaz0 {
+    pub const DEFAULT: Yaz0 = Yaz0 {
+        options: CompressOptions::DEFAULT,
+    };
+}
+
+impl Codec for Yaz0 {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress_with_options(bytes, &self.options)
+    }
 
-                group_size = new_size;
-                group_pos = new_position;
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Ok(Yaz0Header::parse_lenient(bytes)?.uncompressed_size)
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        Yaz0Header::parse_lenient(bytes)?;
+        Ok(Yaz0Header::SIZE)
+    }
+}
+
+/// Decompresses Yaz0 data from a [`Read`](io::Read) stream, producing the same
+/// bytes as [`decompress`] without holding all of them in memory.
+///
+/// The compressed data is read one byte at a time and never past its end, so
+/// unbuffered readers should be wrapped in a [`BufReader`](io::BufReader).
+#[cfg(feature = "std")]
+pub struct Yaz0Decoder<R: io::Read> {
+    inner: R,
+    header: Yaz0Header,
+    history: stream::History,
+    layout_bits: u8,
+    layout_bits_left: u32,
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> Yaz0Decoder<R> {
+    /// Reads the header from `inner`
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let header = Yaz0Header::parse_lenient(&stream::read_up_to(&mut inner, Yaz0Header::SIZE)?)?;
+
+        Ok(Yaz0Decoder {
+            inner,
+            header,
+            history: stream::History::new(header.uncompressed_size),
+            layout_bits: 0,
+            layout_bits_left: 0,
+        })
+    }
+
+    pub fn header(&self) -> &Yaz0Header {
+        &self.header
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> io::Read for Yaz0Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        stream::decode_into(&mut self.history, buf, |history| {
+            if self.layout_bits_left == 0 {
+                self.layout_bits = stream::read_u8(&mut self.inner)?;
+                self.layout_bits_left = 8;
             }
 
-            // Calculate the offset for the current group
-            let group_offset = input_pos as u32 - group_pos - 1;
-
-            // Determine which encoding to use for the current group
-            if group_size >= 0x12 {
-                // Three bytes, 0RRRNN
-                output.push((group_offset >> 8) as u8);
-                index_out_ptr += 1;
-                output.push((group_offset & 0xFF) as u8);
-                index_out_ptr += 1;
-                output.push((group_size - 0x12) as u8);
-                index_out_ptr += 1;
-            } else {
-                // Two bytes, NRRR
-                output.push((group_offset >> 8) as u8 | ((group_size - 2) << 4) as u8);
-                index_out_ptr += 1;
-                output.push((group_offset & 0xFF) as u8);
-                index_out_ptr += 1;
+            let is_literal = (self.layout_bits & 0x80) != 0;
+            self.layout_bits <<= 1;
+            self.layout_bits_left -= 1;
+
+            if is_literal {
+                return Ok(Some(stream::read_u8(&mut self.inner)?));
             }
 
-            // Move forward in the input by the size of the group
-            input_pos += group_size as usize;
+            let first_byte = stream::read_u8(&mut self.inner)?;
+            let second_byte = stream::read_u8(&mut self.inner)?;
+            let byte_pair = ((first_byte as u16) << 8) | (second_byte as u16);
+            let offset = (byte_pair & 0x0FFF) + 1;
+
+            let length = if (first_byte & 0xF0) == 0 {
+                (stream::read_u8(&mut self.inner)? as usize) + 0x12
+            } else {
+                (((byte_pair & 0xF000) >> 12) + 2) as usize
+            };
+
+            history.start_copy(offset as usize, length)?;
+            Ok(None)
+        })
+    }
+}
+
+/// Compresses the data written to it as Yaz0, producing the same bytes as
+/// [`compress`] without holding all of the input in memory.
+///
+/// The header comes first, so the uncompressed size has to be given in
+/// advance. The compressed data is written to the inner writer as it is
+/// produced, and the rest of it by [`Yaz0Encoder::finish`]. Dropping the
+/// encoder finishes it too, but any error is lost.
+#[cfg(feature = "std")]
+pub struct Yaz0Encoder<W: io::Write> {
+    inner: Option<W>,
+    parser: stream::GreedyStream,
+    uncompressed_size: usize,
+    written: usize,
+    // Compressed data that hasn't been written to `inner` yet
+    output: Vec<u8>,
+    writer: GroupWriter,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> Yaz0Encoder<W> {
+    /// Creates an encoder for exactly `uncompressed_size` bytes of input, with
+    /// `alignment` stored in the header like [`CompressOptions::alignment`]
+    pub fn with_size(inner: W, uncompressed_size: usize, alignment: u32) -> Self {
+        let mut output = Vec::new();
+        Yaz0Header {
+            uncompressed_size,
+            alignment,
+        }
+        .write(&mut output);
+
+        Yaz0Encoder {
+            inner: Some(inner),
+            parser: stream::GreedyStream::new(0x111, 1),
+            uncompressed_size,
+            written: 0,
+            output,
+            writer: GroupWriter::default(),
         }
     }
 
-    Ok(output.into_boxed_slice())
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// Compresses the rest of the data, writes it and returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()
+    }
+
+    fn try_finish(&mut self) -> io::Result<W> {
+        let mut inner = self.inner.take().unwrap();
+        if self.written != self.uncompressed_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "less data was written than the size given to the Yaz0 encoder",
+            ));
+        }
+
+        self.parser.finish(|group, literal| {
+            self.writer.write(&mut self.output, group, literal);
+        });
+        inner.write_all(&self.output)?;
+        inner.flush()?;
+        Ok(inner)
+    }
+
+    // Writes the compressed data that no later group can change
+    fn write_complete(&mut self) -> io::Result<()> {
+        let length = self.writer.complete_length(&self.output);
+        self.inner
+            .as_mut()
+            .unwrap()
+            .write_all(&self.output[..length])?;
+        self.output.drain(..length);
+        // The current layout byte is now at the start, unless it was complete too
+        self.writer.layout_index = self.writer.layout_index.saturating_sub(length);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> io::Write for Yaz0Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() > self.uncompressed_size - self.written {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "more data was written than the size given to the Yaz0 encoder",
+            ));
+        }
+        self.written += buf.len();
+
+        self.parser.push(buf, |group, literal| {
+            self.writer.write(&mut self.output, group, literal);
+        });
+        self.write_complete()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Only the complete groups of 8 can be written before the end
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> Drop for Yaz0Encoder<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.try_finish();
+        }
+    }
 }
 
 #[cfg(feature = "c_bindings")]
@@ -195,14 +516,14 @@ mod c_bindings {
             return super::Crunch64Error::NullPointer;
         }
 
-        let bytes = match super::utils::u8_vec_from_pointer_array(0x10, src) {
+        let bytes = match super::utils::u8_slice_from_pointer_array(0x10, src) {
             Err(e) => return e,
             Ok(data) => data,
         };
 
-        match super::parse_header(&bytes) {
+        match super::Yaz0Header::parse_lenient(bytes) {
             Err(e) => return e,
-            Ok(value) => unsafe { *dst_size = value },
+            Ok(header) => unsafe { *dst_size = header.uncompressed_size },
         }
 
         super::Crunch64Error::Okay
@@ -219,18 +540,19 @@ mod c_bindings {
             return super::Crunch64Error::NullPointer;
         }
 
-        let bytes = match super::utils::u8_vec_from_pointer_array(src_len, src) {
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
             Err(e) => return e,
             Ok(d) => d,
         };
 
-        let data = match super::decompress(&bytes) {
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
             Err(e) => return e,
             Ok(d) => d,
         };
 
-        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
-            return e;
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
         }
 
         super::Crunch64Error::Okay
@@ -265,12 +587,79 @@ mod c_bindings {
             return super::Crunch64Error::NullPointer;
         }
 
-        let bytes = match super::utils::u8_vec_from_pointer_array(src_len, src) {
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let data = match super::compress(bytes) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_yaz0_compress_aligned(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+        alignment: u32,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let data = match super::compress_with_options(
+            bytes,
+            &super::CompressOptions {
+                alignment,
+                ..Default::default()
+            },
+        ) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_yaz0_compress_optimal(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
             Err(e) => return e,
             Ok(d) => d,
         };
 
-        let data = match super::compress(&bytes) {
+        let options = super::CompressOptions {
+            optimal: true,
+            ..Default::default()
+        };
+        let data = match super::compress_with_options(bytes, &options) {
             Err(e) => return e,
             Ok(d) => d,
         };
@@ -301,8 +690,16 @@ pub(crate) mod python_bindings {
     }
 
     #[pyfunction]
-    pub(crate) fn compress_yaz0(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
-        Ok(Cow::Owned(super::compress(&bytes)?.into()))
+    #[pyo3(signature = (bytes, alignment=0, optimal=false))]
+    pub(crate) fn compress_yaz0(
+        bytes: Cow<[u8]>,
+        alignment: u32,
+        optimal: bool,
+    ) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        let options = super::CompressOptions { alignment, optimal };
+        Ok(Cow::Owned(
+            super::compress_with_options(&bytes, &options)?.into(),
+        ))
     }
 }
 
@@ -314,7 +711,7 @@ mod tests {
     use rstest::rstest;
     use std::{
         fs::File,
-        io::{BufReader, Read},
+        io::{self, BufReader, Read, Write},
         path::PathBuf,
     };
 
@@ -383,4 +780,176 @@ mod tests {
         );
         Ok(())
     }
+
+    #[rstest]
+    fn test_optimal_compression(
+        #[files("../test_data/*.Yaz0")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let options = super::CompressOptions {
+            optimal: true,
+            ..Default::default()
+        };
+        let compressed = super::compress_with_options(decompressed_file, &options)?;
+        // Never larger than the output of the original encoder
+        assert!(compressed.len() <= compressed_file.len());
+        assert_eq!(decompressed_file, super::decompress(&compressed)?.as_ref());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_header_cycle(
+        #[files("../test_data/*.Yaz0")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let header = super::Yaz0Header::parse(compressed_file)?;
+        assert_eq!(header.uncompressed_size, decompressed_file.len());
+
+        let mut written = Vec::new();
+        header.write(&mut written);
+        assert_eq!(written, &compressed_file[..super::Yaz0Header::SIZE]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_alignment(#[files("../test_data/*.Yaz0")] path: PathBuf) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let mut aligned = compressed_file.clone();
+        aligned[8..0xC].copy_from_slice(&0x80u32.to_be_bytes());
+
+        assert_eq!(
+            super::Yaz0Header::parse(&aligned),
+            Err(Crunch64Error::InvalidYaz0Header)
+        );
+        let header = super::Yaz0Header::parse_lenient(&aligned)?;
+        assert_eq!(header.alignment, 0x80);
+
+        let mut written = Vec::new();
+        header.write(&mut written);
+        assert_eq!(written, &aligned[..super::Yaz0Header::SIZE]);
+
+        // Every decompression path reads the header leniently
+        assert_eq!(decompressed_file, super::decompress(&aligned)?.as_ref());
+        let mut dst = vec![0; decompressed_file.len()];
+        assert_eq!(
+            super::decompress_into(&aligned, &mut dst)?,
+            decompressed_file.len()
+        );
+        assert_eq!(decompressed_file, &dst);
+        let mut decompressed = Vec::new();
+        super::Yaz0Decoder::new(aligned.as_slice())
+            .and_then(|mut decoder| decoder.read_to_end(&mut decompressed))
+            .unwrap();
+        assert_eq!(decompressed_file, &decompressed);
+
+        let options = super::CompressOptions {
+            alignment: 0x80,
+            ..Default::default()
+        };
+        let compressed = super::compress_with_options(decompressed_file, &options)?;
+        assert_eq!(&aligned, compressed.as_ref());
+
+        let mut encoder = super::Yaz0Encoder::with_size(Vec::new(), decompressed_file.len(), 0x80);
+        encoder.write_all(decompressed_file).unwrap();
+        assert_eq!(&aligned, &encoder.finish().unwrap());
+        Ok(())
+    }
+
+    fn yaz0_data(uncompressed_size: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"Yaz0".to_vec();
+        bytes.extend(uncompressed_size.to_be_bytes());
+        bytes.extend([0; 8]);
+        bytes.extend(data);
+        bytes
+    }
+
+    #[rstest]
+    fn test_streaming(#[files("../test_data/*.Yaz0")] path: PathBuf) -> io::Result<()> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        // Read in small pieces to stop in the middle of back-references
+        let mut decoder = super::Yaz0Decoder::new(compressed_file.as_slice())?;
+        let mut decompressed: Vec<u8> = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let read = decoder.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            decompressed.extend(&buf[..read]);
+        }
+        assert_eq!(decompressed_file, &decompressed);
+        assert!(decoder.into_inner().is_empty());
+
+        let mut encoder = super::Yaz0Encoder::with_size(Vec::new(), decompressed_file.len(), 0);
+        for piece in decompressed_file.chunks(0x1234) {
+            encoder.write_all(piece)?;
+        }
+        assert_eq!(compressed_file, &encoder.finish()?);
+
+        // Everything is written in one call, but parsed in pieces
+        let mut encoder = super::Yaz0Encoder::with_size(Vec::new(), decompressed_file.len(), 0);
+        encoder.write_all(decompressed_file)?;
+        assert_eq!(compressed_file, &encoder.finish()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming_encoder() -> io::Result<()> {
+        let data = crate::utils::tests::synthetic_data(0x40000);
+        let compressed = super::compress(&data)?;
+
+        // Complete groups are written before the end of the input
+        let mut encoder = super::Yaz0Encoder::with_size(Vec::new(), data.len(), 0);
+        encoder.write_all(&data[..0x20000])?;
+        let written = encoder.get_ref().len();
+        assert!(written > super::Yaz0Header::SIZE);
+        assert_eq!(encoder.get_ref(), &compressed[..written]);
+        encoder.write_all(&data[0x20000..])?;
+        assert_eq!(compressed.as_ref(), &encoder.finish()?);
+
+        // The input has to match the size given to the encoder
+        let mut encoder = super::Yaz0Encoder::with_size(Vec::new(), 4, 0);
+        assert_eq!(
+            encoder.write_all(b"crunch").unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        encoder.write_all(b"cru")?;
+        assert_eq!(
+            encoder.finish().unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_errors() {
+        // Ends in the middle of a back-reference
+        assert_eq!(
+            super::decompress(&yaz0_data(8, &[0x80, 0x41, 0x10])),
+            Err(Crunch64Error::TruncatedInput)
+        );
+        // Back-reference before the first byte
+        assert_eq!(
+            super::decompress(&yaz0_data(8, &[0x00, 0x10, 0x00])),
+            Err(Crunch64Error::InvalidBackReference)
+        );
+        // Back-reference past the uncompressed size
+        assert_eq!(
+            super::decompress(&yaz0_data(2, &[0x80, 0x41, 0x10, 0x00])),
+            Err(Crunch64Error::OutputOverrun)
+        );
+        // Uncompressed size bigger than the data
+        assert_eq!(
+            super::decompress(&yaz0_data(0xFFFFFFFF, &[0xFF, 0x41])),
+            Err(Crunch64Error::TruncatedInput)
+        );
+    }
 }
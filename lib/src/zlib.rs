@@ -0,0 +1,416 @@
+// Implements zlib (RFC 1950) framing around the matching DEFLATE compression
+// from the gzip module. The compressed data has a 2-byte header and is followed
+// by an Adler-32 checksum of the uncompressed data. Note that the DEFLATE
+// stream is produced by gzip's compression algorithm, which is not always the
+// same as zlib's. See https://datatracker.ietf.org/doc/html/rfc1950 for details
+// on the zlib format.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    gzip,
+    utils::{self, Output, SliceOutput},
+    Codec, Crunch64Error,
+};
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    // Largest number of bytes that can be summed before `b` can overflow
+    const CHUNK_SIZE: usize = 5552;
+
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    for chunk in bytes.chunks(CHUNK_SIZE) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+
+    (b << 16) | a
+}
+
+// Returns the size of the zlib header at the start of `bytes`
+fn parse_header(bytes: &[u8]) -> Result<usize, Crunch64Error> {
+    if bytes.len() < 2 {
+        return Err(Crunch64Error::InvalidZlibHeader);
+    }
+
+    let cmf = bytes[0];
+    let flg = bytes[1];
+
+    // Compression method must be DEFLATE with a window size of at most 32K
+    if cmf & 0x0F != 8 || cmf >> 4 > 7 {
+        return Err(Crunch64Error::InvalidZlibHeader);
+    }
+
+    if u16::from_be_bytes([cmf, flg]) % 31 != 0 {
+        return Err(Crunch64Error::InvalidZlibHeader);
+    }
+
+    // Preset dictionaries are not supported
+    if flg & 0x20 != 0 {
+        return Err(Crunch64Error::InvalidZlibHeader);
+    }
+
+    Ok(2)
+}
+
+fn write_header(dst: &mut Vec<u8>, level: usize) {
+    // Compression method DEFLATE with a 32K window
+    let cmf: u8 = 0x78;
+
+    // Compression level hint, using the same values as zlib
+    let flevel: u8 = match level {
+        0..=1 => 0,
+        2..=5 => 1,
+        6 => 2,
+        _ => 3,
+    };
+
+    let header = ((cmf as u16) << 8) | ((flevel as u16) << 6);
+    let fcheck = 31 - header % 31;
+
+    dst.extend((header + fcheck).to_be_bytes());
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+    Ok(decompress_with_size(bytes)?.0)
+}
+
+/// Like [`decompress`], but also returns the size of the compressed data,
+/// which can be followed by unrelated data in `bytes`
+pub fn decompress_with_size(bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+    let mut output: Vec<u8> = Vec::new();
+    let compressed_size = decompress_to(bytes, &mut output)?;
+
+    Ok((output.into_boxed_slice(), compressed_size))
+}
+
+/// Decompresses `bytes` into the start of `dst`, returning the decompressed size
+pub fn decompress_into(bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+    let mut output = SliceOutput::new(dst);
+    decompress_to(bytes, &mut output)?;
+
+    Ok(output.len())
+}
+
+fn decompress_to<O: Output>(bytes: &[u8], output: &mut O) -> Result<usize, Crunch64Error> {
+    let header_size = parse_header(bytes)?;
+
+    let stream_size = gzip::inflate_into(&bytes[header_size..], output)?;
+
+    let checksum = utils::read_u32(&bytes[header_size + stream_size..], 0)?;
+    if checksum != adler32(output.written()) {
+        return Err(Crunch64Error::ChecksumMismatch);
+    }
+
+    Ok(header_size + stream_size + 4)
+}
+
+fn size_for_compressed_buffer(input_size: usize) -> Result<usize, Crunch64Error> {
+    // Same as gzip, with a 2-byte header and a 4-byte footer instead of an
+    // 8-byte footer
+    Ok(gzip::size_for_compressed_buffer(input_size)? - 2)
+}
+
+pub fn compress(bytes: &[u8], level: usize, small_mem: bool) -> Result<Box<[u8]>, Crunch64Error> {
+    let mut header: Vec<u8> = Vec::with_capacity(2);
+    write_header(&mut header, level);
+
+    let mut output = gzip::deflate(bytes, level, small_mem, &header)?;
+    output.extend(adler32(bytes).to_be_bytes());
+
+    Ok(output.into_boxed_slice())
+}
+
+/// Zlib [`Codec`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Zlib {
+    /// Compression level (1-9)
+    pub level: usize,
+    /// Output compressed blocks more often
+    pub small_mem: bool,
+}
+
+impl Zlib {
+    pub const DEFAULT: Zlib = Zlib {
+        level: 9,
+        small_mem: false,
+    };
+}
+
+impl Default for Zlib {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Codec for Zlib {
+    fn compress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        compress(bytes, self.level, self.small_mem)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Box<[u8]>, Crunch64Error> {
+        decompress(bytes)
+    }
+
+    fn decompress_into(&self, bytes: &[u8], dst: &mut [u8]) -> Result<usize, Crunch64Error> {
+        decompress_into(bytes, dst)
+    }
+
+    fn decompress_with_size(&self, bytes: &[u8]) -> Result<(Box<[u8]>, usize), Crunch64Error> {
+        decompress_with_size(bytes)
+    }
+
+    fn compress_bound(&self, input_size: usize) -> Result<usize, Crunch64Error> {
+        size_for_compressed_buffer(input_size)
+    }
+
+    fn decompress_bound(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        // zlib doesn't store the uncompressed size, so the only way to know it
+        // is to decompress the data
+        Ok(decompress(bytes)?.len())
+    }
+
+    fn header_size(&self, bytes: &[u8]) -> Result<usize, Crunch64Error> {
+        parse_header(bytes)
+    }
+}
+
+#[cfg(feature = "c_bindings")]
+mod c_bindings {
+    use core::ffi::c_int;
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_zlib_decompress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        // zlib streams don't store the uncompressed size, so the only way to
+        // know it is to decompress the data
+        match super::decompress(bytes) {
+            Err(e) => return e,
+            Ok(data) => unsafe { *dst_size = data.len() },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_zlib_decompress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let dst = match super::utils::u8_slice_mut_from_pointer_array(dst_len, dst) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        match super::decompress_into(bytes, dst) {
+            Err(e) => return e,
+            Ok(len) => unsafe { *dst_len = len },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_zlib_compress_bound(
+        dst_size: *mut usize,
+        src_len: usize,
+        src: *const u8,
+    ) -> super::Crunch64Error {
+        if dst_size.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        match super::size_for_compressed_buffer(src_len) {
+            Err(e) => return e,
+            Ok(uncompressed_size) => unsafe { *dst_size = uncompressed_size },
+        }
+
+        super::Crunch64Error::Okay
+    }
+
+    #[no_mangle]
+    pub extern "C" fn crunch64_zlib_compress(
+        dst_len: *mut usize,
+        dst: *mut u8,
+        src_len: usize,
+        src: *const u8,
+        level: c_int,
+        small_mem: bool,
+    ) -> super::Crunch64Error {
+        if dst_len.is_null() || dst.is_null() || src.is_null() {
+            return super::Crunch64Error::NullPointer;
+        }
+
+        let bytes = match super::utils::u8_slice_from_pointer_array(src_len, src) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        let data = match super::compress(bytes, level as usize, small_mem) {
+            Err(e) => return e,
+            Ok(d) => d,
+        };
+
+        if let Err(e) = super::utils::set_pointer_array_from_u8_array(dst_len, dst, &data) {
+            return e;
+        }
+
+        super::Crunch64Error::Okay
+    }
+}
+
+#[cfg(feature = "python_bindings")]
+pub(crate) mod python_bindings {
+    use pyo3::prelude::*;
+    use std::borrow::Cow;
+
+    /**
+     * We use a `Cow` instead of a plain &[u8] because the latter only allows Python's
+     * `bytes` objects, while `Cow`` allows for both `bytes` and `bytearray`.
+     * This is important because an argument typed as `bytes` allows to pass a
+     * `bytearray` object too.
+     */
+
+    #[pyfunction]
+    pub(crate) fn decompress_zlib(bytes: Cow<[u8]>) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(super::decompress(&bytes)?.into()))
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (bytes, level=9, small_mem=false))]
+    pub(crate) fn compress_zlib(
+        bytes: Cow<[u8]>,
+        level: usize,
+        small_mem: bool,
+    ) -> Result<Cow<[u8]>, super::Crunch64Error> {
+        Ok(Cow::Owned(
+            super::compress(&bytes, level, small_mem)?.into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use crate::Crunch64Error;
+    use core::panic;
+    use rstest::rstest;
+    use std::{
+        fs::File,
+        io::{BufReader, Read},
+        path::PathBuf,
+    };
+
+    pub fn read_test_file(path: PathBuf) -> Vec<u8> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(_error) => {
+                panic!("Failed to open file");
+            }
+        };
+
+        let mut buf_reader = BufReader::new(file);
+        let mut buffer = Vec::new();
+
+        let _ = buf_reader.read_to_end(&mut buffer);
+
+        buffer
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(super::adler32(b""), 0x00000001);
+        assert_eq!(super::adler32(b"Wikipedia"), 0x11E60398);
+        assert_eq!(super::adler32(&[0xFF; 0x10000]), 0x77970EF2);
+    }
+
+    #[rstest]
+    fn test_matching_decompression(
+        #[files("../test_data/*.zlib-9")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let decompressed = super::decompress(compressed_file)?;
+        assert_eq!(decompressed_file, decompressed.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompression_bad_checksum() {
+        let mut compressed_file = read_test_file(PathBuf::from("../test_data/small.txt.zlib-9"));
+        let len = compressed_file.len();
+        compressed_file[len - 1] ^= 0xFF;
+
+        assert_eq!(
+            super::decompress(&compressed_file),
+            Err(Crunch64Error::ChecksumMismatch)
+        );
+    }
+
+    #[test]
+    fn test_decompression_bad_header() {
+        let mut compressed_file = read_test_file(PathBuf::from("../test_data/small.txt.zlib-9"));
+        compressed_file[1] ^= 0x01;
+
+        assert_eq!(
+            super::decompress(&compressed_file),
+            Err(Crunch64Error::InvalidZlibHeader)
+        );
+    }
+
+    #[rstest]
+    fn test_matching_compression(
+        #[files("../test_data/*.zlib-9")] path: PathBuf,
+    ) -> Result<(), Crunch64Error> {
+        let compressed_file = &read_test_file(path.clone());
+        let decompressed_file = &read_test_file(path.with_extension(""));
+
+        let compressed = super::compress(decompressed_file.as_slice(), 9, false)?;
+        assert_eq!(compressed_file, compressed.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_levels() {
+        for (level, header) in [
+            (1, [0x78, 0x01]),
+            (5, [0x78, 0x5E]),
+            (6, [0x78, 0x9C]),
+            (9, [0x78, 0xDA]),
+        ] {
+            let mut dst = Vec::new();
+            super::write_header(&mut dst, level);
+            assert_eq!(dst, header);
+            assert_eq!(super::parse_header(&dst), Ok(2));
+        }
+    }
+}
@@ -87,25 +87,179 @@ def run_tests(
 run_tests("yaz0", ".Yaz0", crunch64.yaz0.compress, crunch64.yaz0.decompress)
 run_tests("yay0", ".Yay0", crunch64.yay0.compress, crunch64.yay0.decompress)
 run_tests("mio0", ".MIO0", crunch64.mio0.compress, crunch64.mio0.decompress)
+run_tests(
+    "gzip (level 1)",
+    ".gzip-1",
+    compress=lambda data: crunch64.gzip.compress(data, level=1),
+    decompress=crunch64.gzip.decompress,
+)
+run_tests(
+    "gzip (level 2)",
+    ".gzip-2",
+    compress=lambda data: crunch64.gzip.compress(data, level=2),
+    decompress=crunch64.gzip.decompress,
+)
+run_tests(
+    "gzip (level 3)",
+    ".gzip-3",
+    compress=lambda data: crunch64.gzip.compress(data, level=3),
+    decompress=crunch64.gzip.decompress,
+)
 run_tests(
     "gzip (level 9)",
     ".gzip-9",
     compress=lambda data: crunch64.gzip.compress(data),
-    decompress=None,
+    decompress=crunch64.gzip.decompress,
 )
 run_tests(
     "gzip (level 9, small_mem)",
     ".gzip-9-small-mem",
     compress=lambda data: crunch64.gzip.compress(data, small_mem=True),
-    decompress=None,
+    decompress=crunch64.gzip.decompress,
 )
 run_tests(
     "gzip (level 6, small_mem)",
     ".gzip-6-small-mem",
     compress=lambda data: crunch64.gzip.compress(data, level=6, small_mem=True),
-    decompress=None,
+    decompress=crunch64.gzip.decompress,
+)
+run_tests("zlib (level 9)", ".zlib-9", crunch64.zlib.compress, crunch64.zlib.decompress)
+run_tests("rare", ".rare", crunch64.rare.compress, crunch64.rare.decompress)
+run_tests("vpk0", ".vpk0", crunch64.vpk0.compress, crunch64.vpk0.decompress)
+run_tests(
+    "vpk0 (one-sample)",
+    ".vpk0-one-sample",
+    compress=lambda data: crunch64.vpk0.compress(data, two_sample=False),
+    decompress=crunch64.vpk0.decompress,
+)
+run_tests("lzkn64", ".lzkn64", crunch64.lzkn64.compress, crunch64.lzkn64.decompress)
+run_tests(
+    "lz77 (lz10)",
+    ".lz10",
+    compress=lambda data: crunch64.lz77.compress(data, lz11=False, vram_safe=False),
+    decompress=crunch64.lz77.decompress,
+)
+run_tests(
+    "lz77 (lz10, vram-safe)",
+    ".lz10-vram-safe",
+    compress=lambda data: crunch64.lz77.compress(data, lz11=False, vram_safe=True),
+    decompress=crunch64.lz77.decompress,
+)
+run_tests(
+    "lz77 (lz11)",
+    ".lz11",
+    compress=lambda data: crunch64.lz77.compress(data, lz11=True, vram_safe=False),
+    decompress=crunch64.lz77.decompress,
+)
+run_tests(
+    "lz77 (lz11, vram-safe)",
+    ".lz11-vram-safe",
+    compress=lambda data: crunch64.lz77.compress(data, lz11=True, vram_safe=True),
+    decompress=crunch64.lz77.decompress,
+)
+run_tests("rle", ".rle", crunch64.rle.compress, crunch64.rle.decompress)
+run_tests(
+    "huffman (4-bit)",
+    ".huffman4",
+    compress=lambda data: crunch64.huffman.compress(data, four_bit=True),
+    decompress=crunch64.huffman.decompress,
+)
+run_tests(
+    "huffman (8-bit)",
+    ".huffman8",
+    compress=lambda data: crunch64.huffman.compress(data, four_bit=False),
+    decompress=crunch64.huffman.decompress,
+)
+run_tests(
+    "yay0 (generic)",
+    ".Yay0",
+    compress=lambda data: crunch64.compress("yay0", data),
+    decompress=lambda data: crunch64.decompress("yay0", data),
+)
+run_tests(
+    "rare (generic)",
+    ".rare",
+    compress=lambda data: crunch64.compress("rare", data),
+    decompress=lambda data: crunch64.decompress("rare", data),
 )
 
+print("Testing format detection")
+print()
+for file_extension, format in [
+    (".Yay0", "yay0"),
+    (".Yaz0", "yaz0"),
+    (".MIO0", "mio0"),
+    (".gzip-9", "gzip"),
+    (".zlib-9", "zlib"),
+    (".rare", "rare"),
+    (".vpk0", "vpk0"),
+]:
+    for comp_path in sorted(Path("test_data").glob(f"*{file_extension}")):
+        detected = crunch64.detect(comp_path.read_bytes())
+        if detected != format:
+            print(f"    {comp_path}: expected {format}, got {detected}")
+            errors += 1
+if crunch64.detect(Path("test_data/small.txt").read_bytes()) is not None:
+    print("    test_data/small.txt: expected no format")
+    errors += 1
+print()
+
+print("Testing gzip (with header)")
+print()
+small_txt = Path("test_data/small.txt").read_bytes()
+small_txt_gz = Path("test_data/small.txt.gz").read_bytes()
+if not test_matching_decompression(
+    crunch64.gzip.decompress, small_txt, small_txt_gz
+):
+    errors += 1
+if not test_matching_compression(
+    lambda data: crunch64.gzip.compress(
+        data, header=True, name="small.txt", mtime=1700000000
+    ),
+    small_txt,
+    small_txt_gz,
+):
+    errors += 1
+print()
+
+print("Testing yaz0 (with alignment)")
+print()
+small_txt_yaz0 = Path("test_data/small.txt.Yaz0").read_bytes()
+# Later Nintendo tools store the data alignment at offset 8 of the header
+small_txt_yaz0_aligned = (
+    small_txt_yaz0[:8] + (0x80).to_bytes(4, "big") + small_txt_yaz0[0xC:]
+)
+if not test_matching_decompression(
+    crunch64.yaz0.decompress, small_txt, small_txt_yaz0_aligned
+):
+    errors += 1
+if not test_matching_compression(
+    lambda data: crunch64.yaz0.compress(data, alignment=0x80),
+    small_txt,
+    small_txt_yaz0_aligned,
+):
+    errors += 1
+print()
+
+print("Testing optimal compression")
+print()
+for name, module in (
+    ("yay0", crunch64.yay0),
+    ("yaz0", crunch64.yaz0),
+    ("mio0", crunch64.mio0),
+):
+    print(f"Round-tripping small.txt through {name}... ", end="")
+    compressed = module.compress(small_txt, optimal=True)
+    if module.decompress(compressed) != small_txt:
+        print("decompressed data does not match")
+        errors += 1
+    elif len(compressed) > len(module.compress(small_txt)):
+        print("compressed data is larger than the default encoder's")
+        errors += 1
+    else:
+        print("OK")
+print()
+
 if not errors:
     print("All tests passed")
     exit(0)